@@ -0,0 +1,439 @@
+#![warn(clippy::all, rust_2018_idioms)]
+
+// ウィンドウを開かずに ROM フォルダ内のブロック定義を調べるためのコマンドラインツール
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> std::process::ExitCode {
+    cli::main()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod cli {
+    use std::{fs, path::Path, process::ExitCode};
+    use sw_block_definition_tools::{
+        export, import,
+        sw_block_definition::{
+            attribute_value_label,
+            definition_schema::{AttributeValue, Definition},
+            flag_labels, surface_shape_warnings, SearchQuery, SwBlockDefinition, SwMesh,
+            SwMeshType, XmlElement,
+        },
+        thumbnail::{self, HeadlessGl, ThumbnailRenderer},
+        ui::State,
+    };
+
+    const USAGE: &str = "\
+Usage:
+    swbd list <rom_path>
+    swbd show <rom_path> <filename>
+    swbd check <rom_path>
+    swbd search <rom_path> <query>
+    swbd export-obj <rom_path> <filename> <output.obj>
+    swbd export-glb <rom_path> <filename> <output.glb>
+    swbd thumbnails <rom_path> <output_dir> [size]
+    swbd verify-meshes <rom_path>
+    swbd verify-xml <rom_path>
+    swbd import-mesh <input.obj|.gltf|.glb> <output.mesh|.phys>
+
+Commands:
+    list          List every definition file with its name attribute
+    show          Dump the attributes and meshes of a definition
+    check         Load every definition and its meshes and report errors
+    search        List the definitions matching a query such as \"category=5 has:logic_nodes\"
+    export-obj    Export the meshes and surfaces of a definition to OBJ/MTL
+    export-glb    Export the meshes and surfaces of a definition to glTF binary
+    thumbnails    Render every definition to <output_dir>/<filename>.png (default size 256)
+    verify-meshes Read and rewrite every .mesh/.phys file and compare the bytes
+    verify-xml    Parse, write and parse every definition again and compare the results
+    import-mesh   Convert a triangulated OBJ or glTF model into a mesh file";
+
+    pub fn main() -> ExitCode {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        let result =
+            match args.as_slice() {
+                ["list", rom_path] => open_rom(rom_path).map(|mut state| list(&mut state)),
+                ["show", rom_path, filename] => {
+                    open_rom(rom_path).and_then(|mut state| show(&mut state, filename))
+                }
+                ["check", rom_path] => open_rom(rom_path).map(|mut state| check(&mut state)),
+                ["search", rom_path, query] => {
+                    open_rom(rom_path).map(|mut state| search(&mut state, query))
+                }
+                ["export-obj", rom_path, filename, output] => open_rom(rom_path)
+                    .and_then(|mut state| export_obj(&mut state, filename, output)),
+                ["export-glb", rom_path, filename, output] => open_rom(rom_path)
+                    .and_then(|mut state| export_glb(&mut state, filename, output)),
+                ["thumbnails", rom_path, output_dir] => open_rom(rom_path)
+                    .and_then(|mut state| thumbnails(&mut state, output_dir, "256")),
+                ["thumbnails", rom_path, output_dir, size] => open_rom(rom_path)
+                    .and_then(|mut state| thumbnails(&mut state, output_dir, size)),
+                ["verify-meshes", rom_path] => verify_meshes(rom_path),
+                ["verify-xml", rom_path] => open_rom(rom_path).map(|state| verify_xml(&state)),
+                ["import-mesh", input, output] => import_mesh(input, output),
+                _ => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            };
+
+        match result {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(mes) => {
+                eprintln!("error: {}", mes);
+                ExitCode::FAILURE
+            }
+        }
+    }
+
+    fn open_rom(rom_path: &str) -> Result<State, String> {
+        let mut state = State::default();
+        state
+            .open_rom_directory(rom_path)
+            .map_err(|err| format!("Failed to open {}: {}", rom_path, err))?;
+        state
+            .definitions_mut()
+            .sort_by_key(|definition| definition.filename());
+        Ok(state)
+    }
+
+    fn list(state: &mut State) -> bool {
+        for definition in state.definitions_mut() {
+            let name = match definition.data() {
+                Ok(data) => data.name.clone().unwrap_or_default(),
+                Err(_) => "<error>".to_string(),
+            };
+            println!("{}\t{}", definition.filename(), name);
+        }
+        true
+    }
+
+    fn show(state: &mut State, filename: &str) -> Result<bool, String> {
+        let definition = find_definition(state, filename)?;
+        let data = definition.data().map_err(|err| err.to_string())?;
+
+        for (name, value) in data.attributes() {
+            // 名前の付いた値は "3 (Vehicle Control)" の形で、flags は立っているビットも表示する
+            let label = match value {
+                AttributeValue::I32(Some(v)) => attribute_value_label(name, *v),
+                AttributeValue::U64(Some(v)) if name == "flags" => {
+                    Some(format!("{} [{}]", v, flag_labels(*v)))
+                }
+                _ => None,
+            };
+            if let Some(label) = label.or_else(|| value.is_some().then(|| value.to_string())) {
+                println!("{}\t{}", name, label);
+            }
+        }
+
+        for (name, value) in &data.extra.attributes {
+            println!("{}\t{}\t(unrecognized)", name, value);
+        }
+        for element in &data.extra.elements {
+            println!("<{}>\t(unrecognized)", element.name);
+        }
+
+        let counts: [(&str, usize); 5] = [
+            (
+                "surfaces",
+                data.surfaces.iter().map(|s| s.surface.len()).sum(),
+            ),
+            (
+                "buoyancy_surfaces",
+                data.buoyancy_surfaces.iter().map(|s| s.surface.len()).sum(),
+            ),
+            (
+                "logic_nodes",
+                data.logic_nodes.iter().map(|n| n.logic_node.len()).sum(),
+            ),
+            (
+                "couplings",
+                data.couplings.iter().map(|c| c.coupling.len()).sum(),
+            ),
+            ("voxels", data.voxels.iter().map(|v| v.voxel.len()).sum()),
+        ];
+        for (name, count) in counts {
+            println!("{}\t{}", name, count);
+        }
+
+        let mut ok = true;
+        for (key, mesh) in definition.meshes().iter() {
+            match mesh {
+                Ok(mesh) => match mesh.mesh_type() {
+                    SwMeshType::Mesh => println!("{}\tok", key.xml_name()),
+                    SwMeshType::Phys => println!("{}\tok (phys)", key.xml_name()),
+                },
+                Err(err) => {
+                    println!("{}\terror: {}", key.xml_name(), err);
+                    ok = false;
+                }
+            }
+        }
+        Ok(ok)
+    }
+
+    // GUI と同じくワーカースレッドで全ての定義を並列に読み込む
+    // メッシュは使うときに読み込む
+    fn load_all(state: &mut State) {
+        while state.update_loading() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    fn check(state: &mut State) -> bool {
+        load_all(state);
+        let mut error_count = 0;
+        let total = state.definitions().len();
+
+        for definition in state.definitions_mut() {
+            match definition.data() {
+                Ok(data) => {
+                    for warning in surface_shape_warnings(&data) {
+                        println!("{}\twarning: {}", definition.filename(), warning);
+                    }
+                }
+                Err(err) => {
+                    println!("{}\t{}", definition.filename(), err);
+                    error_count += 1;
+                    continue;
+                }
+            }
+            for (key, mesh) in definition.meshes().iter() {
+                if let Err(err) = mesh {
+                    println!("{}\t{}: {}", definition.filename(), key.xml_name(), err);
+                    error_count += 1;
+                }
+            }
+            // 全ての定義のメッシュを同時にメモリに置かない
+            definition.unload_meshes();
+        }
+
+        eprintln!("{} definitions, {} errors", total, error_count);
+        error_count == 0
+    }
+
+    fn search(state: &mut State, query: &str) -> bool {
+        let query = match SearchQuery::parse(query) {
+            Ok(query) => query,
+            Err(err) => {
+                eprintln!("{}", err);
+                return false;
+            }
+        };
+        load_all(state);
+        let indices = state.search(&query);
+        for &i in &indices {
+            let definition = &mut state.definitions_mut()[i];
+            let name = match definition.data() {
+                Ok(data) => data.name.clone().unwrap_or_default(),
+                Err(_) => "<error>".to_string(),
+            };
+            println!("{}\t{}", definition.filename(), name);
+        }
+        // 一致するものが無いのはエラーではない
+        eprintln!("{} matches", indices.len());
+        true
+    }
+
+    fn export_obj(state: &mut State, filename: &str, output: &str) -> Result<bool, String> {
+        let definition = find_definition(state, filename)?;
+        let groups = export::definition_export_groups(definition).map_err(|err| err.to_string())?;
+        export::export_obj(&groups, output).map_err(|err| err.to_string())?;
+        Ok(true)
+    }
+
+    fn export_glb(state: &mut State, filename: &str, output: &str) -> Result<bool, String> {
+        let definition = find_definition(state, filename)?;
+        let nodes = export::definition_gltf_nodes(definition).map_err(|err| err.to_string())?;
+        export::export_glb(&nodes, output).map_err(|err| err.to_string())?;
+        Ok(true)
+    }
+
+    // EGL のサーフェスなしコンテキストで描画するのでディスプレイが無くても動く
+    fn thumbnails(state: &mut State, output_dir: &str, size: &str) -> Result<bool, String> {
+        let size: u32 = size
+            .parse()
+            .ok()
+            .filter(|size| *size > 0)
+            .ok_or_else(|| format!("Invalid size: {}", size))?;
+        let headless = HeadlessGl::new()?;
+        let mut renderer = ThumbnailRenderer::new(headless.gl(), size);
+        let result =
+            thumbnail::render_thumbnails(&mut renderer, state.definitions_mut(), output_dir);
+        renderer.destroy();
+        let errors = result.map_err(|err| err.to_string())?;
+
+        for (filename, err) in &errors {
+            println!("{}\t{}", filename, err);
+        }
+        eprintln!(
+            "{} thumbnails, {} errors",
+            state.definitions().len() - errors.len(),
+            errors.len()
+        );
+        Ok(errors.is_empty())
+    }
+
+    // メッシュを読み込んで書き戻したときに元のファイルと同じバイト列になるか確かめる
+    fn verify_meshes(rom_path: &str) -> Result<bool, String> {
+        let mut paths = Vec::new();
+        collect_mesh_paths(Path::new(rom_path), &mut paths)
+            .map_err(|err| format!("Failed to read {}: {}", rom_path, err))?;
+        paths.sort();
+
+        let mut error_count = 0;
+        for path in &paths {
+            let result = fs::read(path)
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| {
+                    let mesh = SwMesh::from_bytes(bytes.clone()).map_err(|err| err.to_string())?;
+                    let written = mesh.to_bytes().map_err(|err| err.to_string())?;
+                    match bytes.iter().zip(&written).position(|(a, b)| a != b) {
+                        Some(offset) => Err(format!("differs at offset {}", offset)),
+                        None if bytes.len() != written.len() => Err(format!(
+                            "length differs: {} -> {}",
+                            bytes.len(),
+                            written.len()
+                        )),
+                        None => Ok(()),
+                    }
+                });
+            if let Err(mes) = result {
+                println!("{}\t{}", path.display(), mes);
+                error_count += 1;
+            }
+        }
+
+        eprintln!("{} meshes, {} errors", paths.len(), error_count);
+        Ok(error_count == 0)
+    }
+
+    // 書き出した XML を読み直して元と同じ内容になるか確かめる
+    // 構造体どうしに加えて XML の木どうしも比べ、スキーマに無い内容が失われていないか調べる
+    // 元のファイルと文字単位で一致したものも数える
+    fn verify_xml(state: &State) -> bool {
+        let mut error_count = 0;
+        let mut identical_count = 0;
+        let total = state.definitions().len();
+
+        for definition in state.definitions() {
+            let result = fs::read_to_string(definition.path())
+                .map_err(|err| err.to_string())
+                .and_then(|xml| {
+                    let data = Definition::from_xml(&xml).map_err(|err| err.to_string())?;
+                    let written = data.to_xml().map_err(|err| err.to_string())?;
+                    let reparsed = Definition::from_xml(&written)
+                        .map_err(|err| format!("written XML is invalid: {}", err))?;
+                    if reparsed != data {
+                        return Err("written XML has different contents".to_string());
+                    }
+                    let original =
+                        XmlElement::parse_document(&xml).map_err(|err| err.to_string())?;
+                    let written_tree =
+                        XmlElement::parse_document(&written).map_err(|err| err.to_string())?;
+                    if let Some(path) = content_difference(&original, &written_tree) {
+                        return Err(format!("written XML differs at {}", path));
+                    }
+                    Ok(written == xml)
+                });
+            match result {
+                Ok(true) => identical_count += 1,
+                Ok(false) => {}
+                Err(mes) => {
+                    println!("{}\t{}", definition.filename(), mes);
+                    error_count += 1;
+                }
+            }
+        }
+
+        eprintln!(
+            "{} definitions, {} errors, {} identical to the original",
+            total, error_count, identical_count
+        );
+        error_count == 0
+    }
+
+    // 属性の順序、名前の違う子要素の順序、数値の表記の違いを無視して比べ、
+    // 異なる要素の位置を返す
+    fn content_difference(a: &XmlElement, b: &XmlElement) -> Option<String> {
+        let mut a_attributes: Vec<_> = a.attributes.iter().collect();
+        let mut b_attributes: Vec<_> = b.attributes.iter().collect();
+        a_attributes.sort();
+        b_attributes.sort();
+        let same_value = |x: &str, y: &str| {
+            x == y || matches!((x.parse::<f64>(), y.parse::<f64>()), (Ok(x), Ok(y)) if x == y)
+        };
+        let same_attributes = a_attributes.len() == b_attributes.len()
+            && a_attributes
+                .iter()
+                .zip(&b_attributes)
+                .all(|((an, av), (bn, bv))| an == bn && same_value(av, bv));
+        if a.name != b.name || !same_attributes || a.text != b.text {
+            return Some(format!("<{}>", a.name));
+        }
+
+        let mut a_children: Vec<_> = a.children.iter().collect();
+        let mut b_children: Vec<_> = b.children.iter().collect();
+        a_children.sort_by(|x, y| x.name.cmp(&y.name));
+        b_children.sort_by(|x, y| x.name.cmp(&y.name));
+        if a_children.len() != b_children.len() {
+            return Some(format!("<{}>", a.name));
+        }
+        a_children
+            .iter()
+            .zip(&b_children)
+            .find_map(|(x, y)| content_difference(x, y))
+            .map(|path| format!("<{}>/{}", a.name, path))
+    }
+
+    fn import_mesh(input: &str, output: &str) -> Result<bool, String> {
+        let mesh_type = if output.ends_with(".phys") {
+            SwMeshType::Phys
+        } else {
+            SwMeshType::Mesh
+        };
+        let mesh = import::import_mesh(input, mesh_type).map_err(|err| err.to_string())?;
+        mesh.write_to_file(output).map_err(|err| err.to_string())?;
+        println!(
+            "{} vertices, {} triangles, {} submeshes",
+            mesh.vertices().len(),
+            mesh.triangles().len(),
+            mesh.submeshes().len()
+        );
+        Ok(true)
+    }
+
+    fn collect_mesh_paths(dir: &Path, paths: &mut Vec<std::path::PathBuf>) -> std::io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                collect_mesh_paths(&path, paths)?;
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext == "mesh" || ext == "phys")
+            {
+                paths.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    fn find_definition<'a>(
+        state: &'a mut State,
+        filename: &str,
+    ) -> Result<&'a mut SwBlockDefinition, String> {
+        state
+            .definitions_mut()
+            .iter_mut()
+            .find(|definition| {
+                definition.filename() == filename
+                    || definition.path().file_stem().is_some_and(|s| s == filename)
+            })
+            .ok_or_else(|| format!("Definition not found: {}", filename))
+    }
+}
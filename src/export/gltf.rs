@@ -0,0 +1,402 @@
+use super::definition_surface_mesh;
+use crate::gl_renderer::{Mesh, MeshMaterial, MeshVertex};
+use crate::sw_block_definition::{SwBlockDefinition, SwBlockDefinitionDataError, SwMesh};
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+const GLB_MAGIC: u32 = 0x46546C67; // "glTF"
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A; // "JSON"
+const GLB_CHUNK_BIN: u32 = 0x004E4942; // "BIN\0"
+
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const COMPONENT_FLOAT: u32 = 5126;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const MODE_TRIANGLES: u32 = 4;
+
+const MATERIAL_BASIC: usize = 0;
+const MATERIAL_GLASS: usize = 1;
+
+pub struct GltfNode {
+    pub name: String,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+// 座標はビューアと同じ右手系に変換済み
+pub struct GltfPrimitive {
+    pub name: String,
+    pub shader_id: u16,
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl GltfPrimitive {
+    fn from_mesh(name: String, mesh: &Mesh) -> Self {
+        Self {
+            name,
+            shader_id: match mesh.material() {
+                MeshMaterial::Basic | MeshMaterial::Physics => 0,
+                MeshMaterial::Glass => 1,
+            },
+            vertices: mesh.vertices().clone(),
+            indices: mesh
+                .triangles()
+                .iter()
+                .flatten()
+                .map(|i| *i as u32)
+                .collect(),
+        }
+    }
+
+    fn is_glass(&self) -> bool {
+        self.shader_id == 1
+    }
+}
+
+// サブメッシュごとに使われている頂点だけを取り出し、インデックスを振り直す
+pub fn sw_mesh_primitives(mesh: &SwMesh) -> Vec<GltfPrimitive> {
+    mesh.submeshes()
+        .iter()
+        .map(|submesh| {
+            let mut remap = BTreeMap::new();
+            let mut vertices = Vec::new();
+            let mut indices = Vec::new();
+
+            for triangle in &mesh.triangles()[submesh.triangle_range()] {
+                for i in triangle.as_usize_arr() {
+                    let index = *remap.entry(i).or_insert_with(|| {
+                        vertices.push(mesh.vertices()[i].as_mesh_vertex());
+                        vertices.len() as u32 - 1
+                    });
+                    indices.push(index);
+                }
+            }
+
+            GltfPrimitive {
+                name: submesh.name().unwrap_or_default().to_string(),
+                shader_id: submesh.shader_id(),
+                vertices,
+                indices,
+            }
+        })
+        .collect()
+}
+
+pub fn definition_gltf_nodes(
+    definition: &mut SwBlockDefinition,
+) -> Result<Vec<GltfNode>, SwBlockDefinitionDataError> {
+    let data = definition.data()?;
+    let meshes = definition.meshes();
+    let mut nodes = Vec::new();
+
+    for (key, mesh) in meshes.iter() {
+        if let Ok(mesh) = mesh {
+            nodes.push(GltfNode {
+                name: key.name().to_string(),
+                primitives: sw_mesh_primitives(mesh),
+            });
+        }
+    }
+
+    if let Some(mesh) = definition_surface_mesh(&data) {
+        nodes.push(GltfNode {
+            name: "surfaces".to_string(),
+            primitives: vec![GltfPrimitive::from_mesh("surfaces".to_string(), &mesh)],
+        });
+    }
+
+    Ok(nodes)
+}
+
+pub fn export_glb<P: AsRef<Path>>(nodes: &[GltfNode], path: P) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write_glb(nodes, &mut w)?;
+    w.flush()
+}
+
+pub fn write_glb<W: Write>(nodes: &[GltfNode], w: &mut W) -> io::Result<()> {
+    let mut builder = GltfBuilder::default();
+
+    let node_indices: Vec<usize> = nodes.iter().map(|node| builder.add_node(node)).collect();
+
+    let mut json = json!({
+        "asset": {
+            "version": "2.0",
+            "generator": "sw_block_definition_tools",
+        },
+        "scene": 0,
+        "scenes": [{ "nodes": node_indices }],
+        "nodes": builder.nodes,
+        "materials": [
+            {
+                "name": "basic",
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [1.0, 1.0, 1.0, 1.0],
+                    "metallicFactor": 0.0,
+                    "roughnessFactor": 1.0,
+                },
+            },
+            {
+                "name": "glass",
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [0.627451, 0.627451, 0.78039217, 0.5019608],
+                    "metallicFactor": 0.0,
+                    "roughnessFactor": 0.1,
+                },
+                "alphaMode": "BLEND",
+            },
+        ],
+        "accessors": builder.accessors,
+        "bufferViews": builder.buffer_views,
+        "buffers": [{ "byteLength": builder.bin.len() }],
+    });
+    if builder.meshes.is_empty() {
+        json.as_object_mut().unwrap().remove("buffers");
+    } else {
+        json["meshes"] = Value::Array(builder.meshes);
+    }
+
+    let mut json = serde_json::to_vec(&json)?;
+    pad_to_4(&mut json, b' ');
+    let mut bin = builder.bin;
+    pad_to_4(&mut bin, 0);
+
+    let mut total_length = 12 + 8 + json.len();
+    if !bin.is_empty() {
+        total_length += 8 + bin.len();
+    }
+
+    w.write_all(&GLB_MAGIC.to_le_bytes())?;
+    w.write_all(&GLB_VERSION.to_le_bytes())?;
+    w.write_all(&(total_length as u32).to_le_bytes())?;
+
+    w.write_all(&(json.len() as u32).to_le_bytes())?;
+    w.write_all(&GLB_CHUNK_JSON.to_le_bytes())?;
+    w.write_all(&json)?;
+
+    if !bin.is_empty() {
+        w.write_all(&(bin.len() as u32).to_le_bytes())?;
+        w.write_all(&GLB_CHUNK_BIN.to_le_bytes())?;
+        w.write_all(&bin)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct GltfBuilder {
+    nodes: Vec<Value>,
+    meshes: Vec<Value>,
+    accessors: Vec<Value>,
+    buffer_views: Vec<Value>,
+    bin: Vec<u8>,
+}
+
+impl GltfBuilder {
+    fn add_node(&mut self, node: &GltfNode) -> usize {
+        let primitives: Vec<Value> = node
+            .primitives
+            .iter()
+            .filter(|primitive| !primitive.indices.is_empty())
+            .map(|primitive| self.add_primitive(primitive))
+            .collect();
+
+        let mut value = json!({ "name": node.name });
+        if !primitives.is_empty() {
+            value["mesh"] = json!(self.meshes.len());
+            self.meshes.push(json!({
+                "name": node.name,
+                "primitives": primitives,
+            }));
+        }
+
+        self.nodes.push(value);
+        self.nodes.len() - 1
+    }
+
+    fn add_primitive(&mut self, primitive: &GltfPrimitive) -> Value {
+        let vertices = &primitive.vertices;
+
+        let (min, max) = vertices.iter().fold(
+            ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]),
+            |(min, max), v| {
+                let p = v.position.to_array();
+                (
+                    [0, 1, 2].map(|i| min[i].min(p[i])),
+                    [0, 1, 2].map(|i| max[i].max(p[i])),
+                )
+            },
+        );
+
+        let position = self.add_accessor(
+            vertices.iter().flat_map(|v| v.position.to_array()),
+            "VEC3",
+            Some((min, max)),
+        );
+        let normal = self.add_accessor(
+            vertices.iter().flat_map(|v| v.normal.to_array()),
+            "VEC3",
+            None,
+        );
+        // glTF の COLOR_0 はリニアなので sRGB から変換する
+        let color = self.add_accessor(
+            vertices.iter().flat_map(|v| v.color.to_linear().as_array()),
+            "VEC4",
+            None,
+        );
+        let indices = self.add_index_accessor(&primitive.indices);
+
+        // glTF の primitive には name が無いので extras に入れる
+        json!({
+            "attributes": {
+                "POSITION": position,
+                "NORMAL": normal,
+                "COLOR_0": color,
+            },
+            "indices": indices,
+            "material": if primitive.is_glass() { MATERIAL_GLASS } else { MATERIAL_BASIC },
+            "mode": MODE_TRIANGLES,
+            "extras": {
+                "name": primitive.name,
+                "shader_id": primitive.shader_id,
+            },
+        })
+    }
+
+    fn add_accessor(
+        &mut self,
+        values: impl Iterator<Item = f32>,
+        accessor_type: &str,
+        bounds: Option<([f32; 3], [f32; 3])>,
+    ) -> usize {
+        let components = if accessor_type == "VEC4" { 4 } else { 3 };
+        let (offset, length) = self.push_bytes(values.flat_map(f32::to_le_bytes));
+
+        let buffer_view = self.add_buffer_view(offset, length, TARGET_ARRAY_BUFFER);
+        let mut accessor = json!({
+            "bufferView": buffer_view,
+            "componentType": COMPONENT_FLOAT,
+            "count": length / 4 / components,
+            "type": accessor_type,
+        });
+        if let Some((min, max)) = bounds {
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn add_index_accessor(&mut self, indices: &[u32]) -> usize {
+        let (offset, length) = self.push_bytes(indices.iter().flat_map(|i| i.to_le_bytes()));
+
+        let buffer_view = self.add_buffer_view(offset, length, TARGET_ELEMENT_ARRAY_BUFFER);
+        self.accessors.push(json!({
+            "bufferView": buffer_view,
+            "componentType": COMPONENT_UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+
+    fn add_buffer_view(&mut self, offset: usize, length: usize, target: u32) -> usize {
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": length,
+            "target": target,
+        }));
+        self.buffer_views.len() - 1
+    }
+
+    fn push_bytes(&mut self, bytes: impl Iterator<Item = u8>) -> (usize, usize) {
+        let offset = self.bin.len();
+        self.bin.extend(bytes);
+        (offset, self.bin.len() - offset)
+    }
+}
+
+fn pad_to_4(data: &mut Vec<u8>, value: u8) {
+    while data.len() % 4 != 0 {
+        data.push(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gl_renderer::Color4;
+    use crate::import::parse_gltf;
+    use glam::Vec3;
+
+    fn triangle(name: &str, shader_id: u16, offset: f32, color: Color4) -> GltfPrimitive {
+        GltfPrimitive {
+            name: name.to_string(),
+            shader_id,
+            vertices: [
+                Vec3::new(offset, 0.0, 0.0),
+                Vec3::new(offset + 1.0, 0.0, 0.0),
+                Vec3::new(offset, 1.0, -1.0),
+            ]
+            .into_iter()
+            .map(|position| MeshVertex {
+                position,
+                color,
+                normal: Vec3::Z,
+            })
+            .collect(),
+            indices: vec![0, 1, 2],
+        }
+    }
+
+    #[test]
+    fn round_trips_through_importer() {
+        let colors = [
+            Color4::new(0.5, 0.25, 1.0, 1.0),
+            Color4::new(0.0, 0.75, 0.1, 0.5),
+        ];
+        let nodes = vec![GltfNode {
+            name: "mesh".to_string(),
+            primitives: vec![
+                triangle("body", 0, 0.0, colors[0]),
+                triangle("window", 1, 2.0, colors[1]),
+            ],
+        }];
+
+        let mut bytes = Vec::new();
+        write_glb(&nodes, &mut bytes).unwrap();
+        let submeshes = parse_gltf(&bytes, Path::new("")).unwrap();
+
+        assert_eq!(submeshes.len(), 2);
+        for ((submesh, primitive), color) in submeshes.iter().zip(&nodes[0].primitives).zip(colors)
+        {
+            assert_eq!(submesh.name, primitive.name);
+            assert_eq!(submesh.shader_id, primitive.shader_id);
+            assert_eq!(submesh.indices, primitive.indices);
+            assert_eq!(submesh.vertices.len(), primitive.vertices.len());
+            for (imported, original) in submesh.vertices.iter().zip(&primitive.vertices) {
+                assert_eq!(imported.position, original.position);
+                for (a, b) in imported.color.as_array().into_iter().zip(color.as_array()) {
+                    assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn converts_colors_to_linear() {
+        let linear = Color4::new(0.5, 0.0, 1.0, 0.5).to_linear();
+        assert!((linear.r - 0.2140).abs() < 1e-4);
+        assert_eq!(linear.g, 0.0);
+        assert!((linear.b - 1.0).abs() < 1e-6);
+        assert_eq!(linear.a, 0.5);
+    }
+}
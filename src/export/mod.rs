@@ -0,0 +1,64 @@
+mod gltf;
+pub use gltf::{
+    definition_gltf_nodes, export_glb, sw_mesh_primitives, write_glb, GltfNode, GltfPrimitive,
+};
+mod obj;
+pub use obj::{export_obj, write_mtl, write_obj};
+
+use crate::gl_renderer::Mesh;
+use crate::sw_block_definition::{
+    create_surface_mesh, definition_schema::Definition, SwBlockDefinition,
+    SwBlockDefinitionDataError,
+};
+
+// 書き出し単位となるメッシュ
+// 座標はビューアと同じ右手系に変換済み
+pub struct ExportGroup {
+    pub name: String,
+    pub mesh: Mesh,
+}
+
+pub fn definition_export_groups(
+    definition: &mut SwBlockDefinition,
+) -> Result<Vec<ExportGroup>, SwBlockDefinitionDataError> {
+    let data = definition.data()?;
+    let meshes = definition.meshes();
+    let mut groups = Vec::new();
+
+    for (key, mesh) in meshes.iter() {
+        if let Ok(mesh) = mesh {
+            for (i, (submesh, m)) in mesh.submeshes().iter().zip(mesh.as_meshes()).enumerate() {
+                let name = match submesh.name() {
+                    Some(name) if !name.is_empty() => format!("{}.{}", key.name(), name),
+                    _ => format!("{}.{}", key.name(), i),
+                };
+                groups.push(ExportGroup { name, mesh: m });
+            }
+        }
+    }
+
+    if let Some(mesh) = definition_surface_mesh(&data) {
+        groups.push(ExportGroup {
+            name: "surfaces".to_string(),
+            mesh,
+        });
+    }
+
+    Ok(groups)
+}
+
+// 最後の surfaces の面を 1 つのメッシュにまとめる。面が無ければ None
+fn definition_surface_mesh(data: &Definition) -> Option<Mesh> {
+    let surface_meshes: Vec<Mesh> = data
+        .surfaces
+        .last()?
+        .surface
+        .iter()
+        .filter_map(create_surface_mesh)
+        .collect();
+    if surface_meshes.is_empty() {
+        None
+    } else {
+        Some(Mesh::combined(surface_meshes))
+    }
+}
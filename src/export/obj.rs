@@ -0,0 +1,154 @@
+use super::ExportGroup;
+use crate::gl_renderer::MeshMaterial;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+const MATERIAL_BASIC: &str = "basic";
+const MATERIAL_GLASS: &str = "glass";
+
+// path に .obj を、同じディレクトリに同名の .mtl を書き出す
+pub fn export_obj<P: AsRef<Path>>(groups: &[ExportGroup], path: P) -> io::Result<()> {
+    let obj_path = path.as_ref().with_extension("obj");
+    let mtl_path = path.as_ref().with_extension("mtl");
+    let mtl_filename = mtl_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid file name"))?;
+
+    let mut obj = BufWriter::new(File::create(&obj_path)?);
+    write_obj(groups, &mut obj, mtl_filename)?;
+    obj.flush()?;
+
+    let mut mtl = BufWriter::new(File::create(&mtl_path)?);
+    write_mtl(&mut mtl)?;
+    mtl.flush()
+}
+
+pub fn write_obj<W: Write>(
+    groups: &[ExportGroup],
+    w: &mut W,
+    mtl_filename: &str,
+) -> io::Result<()> {
+    writeln!(w, "# Exported by sw_block_definition_tools")?;
+    writeln!(w, "mtllib {}", mtl_filename)?;
+
+    // OBJ のインデックスは 1 始まりでファイル全体で通し番号
+    let mut index_offset = 1;
+
+    for group in groups {
+        let vertices = group.mesh.vertices();
+
+        writeln!(w, "g {}", group.name.replace(char::is_whitespace, "_"))?;
+        writeln!(
+            w,
+            "usemtl {}",
+            match group.mesh.material() {
+                MeshMaterial::Basic | MeshMaterial::Physics => MATERIAL_BASIC,
+                MeshMaterial::Glass => MATERIAL_GLASS,
+            }
+        )?;
+
+        // 頂点カラーは広く使われている "v x y z r g b" 形式の拡張で出力する
+        for v in vertices {
+            writeln!(
+                w,
+                "v {} {} {} {} {} {}",
+                v.position.x, v.position.y, v.position.z, v.color.r, v.color.g, v.color.b
+            )?;
+        }
+        for v in vertices {
+            writeln!(w, "vn {} {} {}", v.normal.x, v.normal.y, v.normal.z)?;
+        }
+        for indices in group.mesh.triangles() {
+            let [a, b, c] = indices.map(|i| i + index_offset);
+            writeln!(w, "f {a}//{a} {b}//{b} {c}//{c}")?;
+        }
+
+        index_offset += vertices.len();
+    }
+
+    Ok(())
+}
+
+pub fn write_mtl<W: Write>(w: &mut W) -> io::Result<()> {
+    writeln!(w, "# Exported by sw_block_definition_tools")?;
+    writeln!(w, "newmtl {}", MATERIAL_BASIC)?;
+    writeln!(w, "Kd 1 1 1")?;
+    writeln!(w, "d 1")?;
+    writeln!(w, "illum 1")?;
+    writeln!(w)?;
+    writeln!(w, "newmtl {}", MATERIAL_GLASS)?;
+    writeln!(w, "Kd 0.627451 0.627451 0.78039217")?;
+    writeln!(w, "d 0.5019608")?;
+    writeln!(w, "Tr 0.4980392")?;
+    writeln!(w, "illum 4")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gl_renderer::{Color4, Mesh, MeshVertex};
+    use glam::Vec3;
+
+    fn triangle(x: f32) -> Mesh {
+        let vertices = [Vec3::new(x, 0.0, 0.0), Vec3::X, Vec3::Y]
+            .into_iter()
+            .map(|position| MeshVertex {
+                position,
+                color: Color4::new(1.0, 0.5, 0.0, 1.0),
+                normal: Vec3::Z,
+            })
+            .collect();
+        Mesh::new(vertices, vec![[0, 1, 2]])
+    }
+
+    #[test]
+    fn writes_groups_with_one_based_indices() {
+        let mut glass = triangle(2.0);
+        glass.glass();
+        let groups = [
+            ExportGroup {
+                name: "mesh body".to_string(),
+                mesh: triangle(0.0),
+            },
+            ExportGroup {
+                name: "window".to_string(),
+                mesh: glass,
+            },
+        ];
+
+        let mut out = Vec::new();
+        write_obj(&groups, &mut out, "test.mtl").unwrap();
+        let lines: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+
+        assert_eq!(
+            lines,
+            [
+                "# Exported by sw_block_definition_tools",
+                "mtllib test.mtl",
+                "g mesh_body",
+                "usemtl basic",
+                "v 0 0 0 1 0.5 0",
+                "v 1 0 0 1 0.5 0",
+                "v 0 1 0 1 0.5 0",
+                "vn 0 0 1",
+                "vn 0 0 1",
+                "vn 0 0 1",
+                "f 1//1 2//2 3//3",
+                "g window",
+                "usemtl glass",
+                "v 2 0 0 1 0.5 0",
+                "v 1 0 0 1 0.5 0",
+                "v 0 1 0 1 0.5 0",
+                "vn 0 0 1",
+                "vn 0 0 1",
+                "vn 0 0 1",
+                "f 4//4 5//5 6//6",
+            ]
+        );
+    }
+}
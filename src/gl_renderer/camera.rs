@@ -10,7 +10,7 @@ pub trait Camera {
     fn position(&self) -> Vec3;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OrbitCamera {
     pub center: Vec3,
     pub direction: Vec3,
@@ -45,4 +45,40 @@ impl Color4 {
     pub fn as_array(&self) -> [f32; 4] {
         [self.r, self.g, self.b, self.a]
     }
+
+    // sRGB の色をリニアに変換する。アルファはそのまま
+    pub fn to_linear(self) -> Self {
+        Self::new(
+            srgb_to_linear(self.r),
+            srgb_to_linear(self.g),
+            srgb_to_linear(self.b),
+            self.a,
+        )
+    }
+
+    // リニアの色を sRGB に変換する。アルファはそのまま
+    pub fn to_srgb(self) -> Self {
+        Self::new(
+            linear_to_srgb(self.r),
+            linear_to_srgb(self.g),
+            linear_to_srgb(self.b),
+            self.a,
+        )
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
 }
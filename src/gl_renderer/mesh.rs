@@ -1,11 +1,12 @@
 use super::{Color4, GlConfig, SceneObjectContent, ShaderAttributeData, ShaderType};
 use eframe::glow;
-use glam::Vec3;
+use glam::{Mat4, Vec3};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MeshMaterial {
     Basic,
     Glass,
+    Physics,
 }
 
 impl MeshMaterial {
@@ -13,6 +14,7 @@ impl MeshMaterial {
         match self {
             Self::Basic => ShaderType::Basic,
             Self::Glass => ShaderType::Glass,
+            Self::Physics => ShaderType::Physics,
         }
     }
 }
@@ -135,9 +137,62 @@ impl Mesh {
     pub fn glass(&mut self) {
         self.material = MeshMaterial::Glass;
     }
+
+    pub fn physics(&mut self) {
+        self.material = MeshMaterial::Physics;
+    }
+
+    // 三角形の向きと法線を反転する
+    pub fn reversed(mut self) -> Self {
+        for v in &mut self.vertices {
+            v.normal = -v.normal;
+        }
+        for indices in &mut self.triangles {
+            indices.swap(1, 2);
+        }
+        self
+    }
+
+    // 裏向きの三角形を足して両面から見えるようにする
+    pub fn two_sided(self) -> Self {
+        let material = self.material;
+        let back = Self {
+            vertices: self.vertices.clone(),
+            triangles: self.triangles.clone(),
+            material,
+            center: self.center,
+        }
+        .reversed();
+        let mut mesh = Self::combined([self, back]);
+        mesh.material = material;
+        mesh
+    }
+
+    pub fn transformed(mut self, transform_matrix: &Mat4) -> Self {
+        for v in &mut self.vertices {
+            v.position = transform_matrix.transform_point3(v.position);
+            v.normal = transform_matrix
+                .transform_vector3(v.normal)
+                .normalize_or_zero();
+        }
+        self.center = transform_matrix.transform_point3(self.center);
+        self
+    }
+
+    pub fn vertices(&self) -> &Vec<MeshVertex> {
+        &self.vertices
+    }
+
+    pub fn triangles(&self) -> &Vec<[usize; 3]> {
+        &self.triangles
+    }
+
+    pub fn material(&self) -> MeshMaterial {
+        self.material
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MeshVertex {
     pub position: Vec3,
     pub color: Color4,
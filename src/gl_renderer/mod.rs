@@ -5,10 +5,14 @@ pub use scene::{Scene, SceneObject};
 mod color4;
 pub use color4::Color4;
 mod mesh;
-pub use mesh::{Mesh, MeshVertex};
+pub use mesh::{Mesh, MeshMaterial, MeshVertex};
 mod line;
-pub use line::Line;
+pub use line::{Line, LineVertex};
 mod camera;
 pub use camera::{Camera, OrbitCamera};
+mod multisample_framebuffer;
+pub use multisample_framebuffer::MultisampleFramebuffer;
+mod picking;
+pub use picking::{PickShape, Ray};
 mod shader_type;
 pub use shader_type::{SceneObjectContent, ShaderAttributeData, ShaderType};
@@ -1,4 +1,4 @@
-use eframe::glow::{self, HasContext, NativeFramebuffer};
+use eframe::glow::{self, HasContext};
 use image::ImageBuffer;
 use std::sync::Arc;
 
@@ -6,13 +6,43 @@ pub struct MultisampleFramebuffer {
     gl: Arc<glow::Context>,
     width: i32,
     height: i32,
-    framebuffer_multisample: NativeFramebuffer,
-    framebuffer_resolve: NativeFramebuffer,
+    framebuffer_multisample: glow::Framebuffer,
+    framebuffer_resolve: glow::Framebuffer,
+    textures: [glow::Texture; 2],
+    depthbuffer: glow::Renderbuffer,
 }
 
 impl MultisampleFramebuffer {
-    pub fn new(gl: Arc<glow::Context>, width: i32, height: i32, samples: i32) -> Self {
+    // 作れる幅と高さの上限
+    pub fn max_size(gl: &glow::Context) -> i32 {
         unsafe {
+            gl.get_parameter_i32(glow::MAX_RENDERBUFFER_SIZE)
+                .min(gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE))
+        }
+    }
+
+    // 大きすぎるサイズやメモリ不足で作れなかったときはエラーを返す
+    pub fn new(
+        gl: Arc<glow::Context>,
+        width: i32,
+        height: i32,
+        samples: i32,
+    ) -> Result<Self, String> {
+        let max_size = Self::max_size(&gl);
+        if width <= 0 || height <= 0 || width > max_size || height > max_size {
+            return Err(format!(
+                "Framebuffer size {}×{} is out of range (max {}).",
+                width, height, max_size
+            ));
+        }
+
+        unsafe {
+            // 前の描画で残ったエラーを捨てる
+            while gl.get_error() != glow::NO_ERROR {}
+
+            // ソフトウェアレンダラなどは対応するサンプル数が少ない
+            let samples = samples.clamp(1, gl.get_parameter_i32(glow::MAX_SAMPLES).max(1));
+
             let framebuffer_multisample = gl
                 .create_framebuffer()
                 .expect("Failed to create framebuffer");
@@ -54,10 +84,7 @@ impl MultisampleFramebuffer {
                 Some(depthbuffer_multisample),
             );
 
-            assert_eq!(
-                gl.check_framebuffer_status(glow::FRAMEBUFFER),
-                glow::FRAMEBUFFER_COMPLETE
-            );
+            let status_multisample = gl.check_framebuffer_status(glow::FRAMEBUFFER);
 
             let framebuffer_resolve = gl
                 .create_framebuffer()
@@ -73,7 +100,7 @@ impl MultisampleFramebuffer {
                 width,
                 height,
                 0,
-                glow::RGB,
+                glow::RGBA,
                 glow::UNSIGNED_BYTE,
                 glow::PixelUnpackData::Slice(None),
             );
@@ -95,27 +122,36 @@ impl MultisampleFramebuffer {
                 0,
             );
 
-            assert_eq!(
-                gl.check_framebuffer_status(glow::FRAMEBUFFER),
-                glow::FRAMEBUFFER_COMPLETE
-            );
+            let status_resolve = gl.check_framebuffer_status(glow::FRAMEBUFFER);
 
             gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            let error = gl.get_error();
 
-            Self {
+            // 失敗したときは Drop で削除される
+            let framebuffer = Self {
                 gl,
                 width,
                 height,
                 framebuffer_multisample,
                 framebuffer_resolve,
+                textures: [texture_multisample, texture_resolve],
+                depthbuffer: depthbuffer_multisample,
+            };
+            if error != glow::NO_ERROR {
+                return Err(format!(
+                    "Failed to create framebuffer: GL error 0x{:X}",
+                    error
+                ));
+            }
+            for status in [status_multisample, status_resolve] {
+                if status != glow::FRAMEBUFFER_COMPLETE {
+                    return Err(format!("Framebuffer is incomplete: status 0x{:X}", status));
+                }
             }
+            Ok(framebuffer)
         }
     }
 
-    pub fn gl(&self) -> Arc<glow::Context> {
-        self.gl.clone()
-    }
-
     pub fn bind(&self) {
         unsafe {
             self.gl
@@ -182,3 +218,16 @@ impl MultisampleFramebuffer {
         img
     }
 }
+
+impl Drop for MultisampleFramebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_framebuffer(self.framebuffer_multisample);
+            self.gl.delete_framebuffer(self.framebuffer_resolve);
+            for texture in self.textures {
+                self.gl.delete_texture(texture);
+            }
+            self.gl.delete_renderbuffer(self.depthbuffer);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use super::{Camera, Mesh};
+use glam::{Vec2, Vec3, Vec4Swizzles};
+
+// 画面上の点から視線方向に伸ばした半直線
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+// クリックで選べる形状 (ビューアの右手系)
+#[derive(Debug, Clone)]
+pub enum PickShape {
+    Triangles(Vec<[Vec3; 3]>),
+    Box { min: Vec3, max: Vec3 },
+}
+
+impl Ray {
+    // ndc は -1..1 の正規化デバイス座標 (y は上向き)
+    pub fn from_camera(camera: &impl Camera, ndc: Vec2) -> Self {
+        let inverse = camera.mat_view_proj().inverse();
+        let near = inverse * ndc.extend(0.0).extend(1.0);
+        let far = inverse * ndc.extend(1.0).extend(1.0);
+        let near = near.xyz() / near.w;
+        let far = far.xyz() / far.w;
+        Self {
+            origin: near,
+            direction: (far - near).normalize(),
+        }
+    }
+
+    // 当たった位置までの距離
+    pub fn intersect(&self, shape: &PickShape) -> Option<f32> {
+        match shape {
+            PickShape::Triangles(triangles) => triangles
+                .iter()
+                .filter_map(|triangle| self.intersect_triangle(triangle))
+                .min_by(|a, b| a.total_cmp(b)),
+            PickShape::Box { min, max } => self.intersect_box(*min, *max),
+        }
+    }
+
+    // Möller–Trumbore 法。裏面にも当たる
+    fn intersect_triangle(&self, [p0, p1, p2]: &[Vec3; 3]) -> Option<f32> {
+        let e1 = *p1 - *p0;
+        let e2 = *p2 - *p0;
+        let p = self.direction.cross(e2);
+        let det = e1.dot(p);
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let t_vec = self.origin - *p0;
+        let u = t_vec.dot(p) / det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = t_vec.cross(e1);
+        let v = self.direction.dot(q) / det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = e2.dot(q) / det;
+        (t > 0.0).then_some(t)
+    }
+
+    // スラブ法
+    fn intersect_box(&self, min: Vec3, max: Vec3) -> Option<f32> {
+        let inv = self.direction.recip();
+        let t0 = (min - self.origin) * inv;
+        let t1 = (max - self.origin) * inv;
+        let t_near = t0.min(t1).max_element();
+        let t_far = t0.max(t1).min_element();
+        (t_near <= t_far && t_far > 0.0).then_some(t_near.max(0.0))
+    }
+}
+
+impl PickShape {
+    pub fn from_mesh(mesh: &Mesh) -> Self {
+        let vertices = mesh.vertices();
+        Self::Triangles(
+            mesh.triangles()
+                .iter()
+                .map(|indices| indices.map(|i| vertices[i].position))
+                .collect(),
+        )
+    }
+
+    // 中心と半分の大きさで表す箱
+    pub fn cube(center: Vec3, half_size: f32) -> Self {
+        Self::Box {
+            min: center - Vec3::splat(half_size),
+            max: center + Vec3::splat(half_size),
+        }
+    }
+
+    // 強調表示用の輪郭線
+    pub fn edges(&self) -> Vec<(Vec3, Vec3)> {
+        match self {
+            Self::Triangles(triangles) => triangles
+                .iter()
+                .flat_map(|[p0, p1, p2]| [(*p0, *p1), (*p1, *p2), (*p2, *p0)])
+                .collect(),
+            Self::Box { min, max } => {
+                let corner = |i: usize| {
+                    Vec3::new(
+                        if i & 1 == 0 { min.x } else { max.x },
+                        if i & 2 == 0 { min.y } else { max.y },
+                        if i & 4 == 0 { min.z } else { max.z },
+                    )
+                };
+                (0..8)
+                    .flat_map(|i| [1, 2, 4].map(|bit| (i, bit)))
+                    .filter(|(i, bit)| i & bit == 0)
+                    .map(|(i, bit)| (corner(i), corner(i | bit)))
+                    .collect()
+            }
+        }
+    }
+}
@@ -1,8 +1,9 @@
-use super::{Camera, Scene, ShaderType};
+use super::{Camera, Color4, MultisampleFramebuffer, Scene, ShaderType};
 use eframe::glow::{self, HasContext};
 use egui::mutex::Mutex;
 use enum_map::EnumMap;
 use glam::{Mat4, Vec3, Vec4};
+use image::RgbaImage;
 use std::sync::Arc;
 
 const SKY_COLOR_UP: Vec3 = Vec3 {
@@ -22,6 +23,8 @@ pub struct SceneRenderer {
     vaos: Vec<VaoContainer>,
     render_error: Option<String>,
     scene: Arc<Mutex<Scene>>,
+    // 塗装できる部分の色 (ペイントスロット 1～3)
+    override_colors: [Vec4; 3],
 }
 
 #[allow(unsafe_code)]
@@ -32,9 +35,14 @@ impl SceneRenderer {
             vaos: Vec::new(),
             render_error: None,
             scene,
+            override_colors: [Vec4::ONE; 3],
         }
     }
 
+    pub fn set_override_colors(&mut self, colors: [Color4; 3]) {
+        self.override_colors = colors.map(|c| Vec4::from_array(c.as_array()));
+    }
+
     pub fn destroy(&self, gl: Option<&eframe::glow::Context>) {
         use glow::HasContext as _;
 
@@ -50,12 +58,31 @@ impl SceneRenderer {
         }
     }
 
+    // 画面ではなく framebuffer に描画して画像として読み出す
+    // 背景は clear_color で塗りつぶす (透明にもできる)
+    pub fn paint_offscreen(
+        &mut self,
+        gl: &glow::Context,
+        camera: Arc<Mutex<impl Camera>>,
+        framebuffer: &MultisampleFramebuffer,
+        clear_color: Color4,
+    ) -> RgbaImage {
+        use glow::HasContext as _;
+
+        framebuffer.bind();
+        unsafe {
+            gl.clear_color(clear_color.r, clear_color.g, clear_color.b, clear_color.a);
+            gl.clear(glow::COLOR_BUFFER_BIT);
+        }
+        self.paint(gl, camera);
+        framebuffer.resolve();
+        framebuffer.get_image()
+    }
+
     pub fn paint(&mut self, gl: &glow::Context, camera: Arc<Mutex<impl Camera>>) {
         use glow::HasContext as _;
 
-        let override_color_1 = Vec4::ONE;
-        let override_color_2 = Vec4::ONE;
-        let override_color_3 = Vec4::ONE;
+        let [override_color_1, override_color_2, override_color_3] = self.override_colors;
 
         let mat_view_proj = camera.lock().mat_view_proj();
         let camera_position = camera.lock().position();
@@ -97,6 +124,16 @@ impl SceneRenderer {
             gl.cull_face(glow::BACK);
             gl.front_face(glow::CCW);
 
+            // 半透明オブジェクトは乗算済みアルファで合成する
+            gl.enable(glow::BLEND);
+            gl.blend_equation(glow::FUNC_ADD);
+            gl.blend_func_separate(
+                glow::ONE,
+                glow::ONE_MINUS_SRC_ALPHA,
+                glow::ONE_MINUS_DST_ALPHA,
+                glow::ONE,
+            );
+
             #[cfg(not(target_arch = "wasm32"))]
             gl.enable(glow::MULTISAMPLE);
 
@@ -13,6 +13,14 @@ const GLASS_SHADER_SOURCES: [(u32, &str); 2] = [
     (glow::FRAGMENT_SHADER, include_str!("./shaders/glass.frag")),
 ];
 
+const PHYSICS_SHADER_SOURCES: [(u32, &str); 2] = [
+    (glow::VERTEX_SHADER, include_str!("./shaders/physics.vert")),
+    (
+        glow::FRAGMENT_SHADER,
+        include_str!("./shaders/physics.frag"),
+    ),
+];
+
 const LINE_SHADER_SOURCES: [(u32, &str); 2] = [
     (glow::VERTEX_SHADER, include_str!("./shaders/line.vert")),
     (glow::FRAGMENT_SHADER, include_str!("./shaders/line.frag")),
@@ -22,12 +30,13 @@ const LINE_SHADER_SOURCES: [(u32, &str); 2] = [
 pub enum ShaderType {
     Basic,
     Glass,
+    Physics,
     Line,
 }
 
 impl ShaderType {
     pub fn is_translucent(self) -> bool {
-        self == Self::Glass
+        self == Self::Glass || self == Self::Physics
     }
 
     pub fn create_program(&self, gl: &glow::Context) -> Option<glow::Program> {
@@ -36,6 +45,7 @@ impl ShaderType {
         let shader_sources = match self {
             Self::Basic => BASIC_SHADER_SOURCES,
             Self::Glass => GLASS_SHADER_SOURCES,
+            Self::Physics => PHYSICS_SHADER_SOURCES,
             Self::Line => LINE_SHADER_SOURCES,
         };
 
@@ -95,6 +105,7 @@ impl ShaderType {
         enum_map! {
             Self::Basic => Self::Basic.create_program(gl).expect("Failed to create shader program"),
             Self::Glass => Self::Glass.create_program(gl).expect("Failed to create shader program"),
+            Self::Physics => Self::Physics.create_program(gl).expect("Failed to create shader program"),
             Self::Line => Self::Line.create_program(gl).expect("Failed to create shader program"),
         }
     }
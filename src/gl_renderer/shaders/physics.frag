@@ -0,0 +1,16 @@
+#ifdef GL_ES
+precision highp float;
+#endif
+
+in vec4 vertex_color_out;
+in vec3 vertex_normal_out;
+
+out vec4 color_out;
+
+void main()
+{
+    vec3 light_dir = vec3(0.5, -1.0, 0.2);
+    float light_amount = dot(vertex_normal_out, -light_dir) * 0.4 + 0.7;
+    // 乗算済みアルファで出力する
+    color_out = vec4(vertex_color_out.rgb * light_amount * vertex_color_out.a, vertex_color_out.a);
+}
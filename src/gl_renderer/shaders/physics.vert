@@ -0,0 +1,16 @@
+in vec3 vertex_position_in;
+in vec4 vertex_color_in;
+in vec3 vertex_normal_in;
+
+out vec4 vertex_color_out;
+out vec3 vertex_normal_out;
+
+uniform mat4 mat_view_proj;
+uniform mat4 mat_world;
+
+void main()
+{
+    gl_Position =  mat_view_proj * mat_world * vec4(vertex_position_in, 1);
+    vertex_color_out = vertex_color_in;
+    vertex_normal_out = (mat_world * vec4(vertex_normal_in, 0)).xyz;
+}
@@ -0,0 +1,598 @@
+use super::{shader_id_for_material, ImportError, ImportSubmesh, SHADER_BASIC, SHADER_GLASS};
+use crate::gl_renderer::{Color4, MeshVertex};
+use base64::Engine;
+use glam::{Mat3, Mat4, Quat, Vec3};
+use serde_json::Value;
+use std::{fs, path::Path};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A; // "JSON"
+const GLB_CHUNK_BIN: u32 = 0x004E4942; // "BIN\0"
+
+const COMPONENT_BYTE: u64 = 5120;
+const COMPONENT_UNSIGNED_BYTE: u64 = 5121;
+const COMPONENT_SHORT: u64 = 5122;
+const COMPONENT_UNSIGNED_SHORT: u64 = 5123;
+const COMPONENT_UNSIGNED_INT: u64 = 5125;
+const COMPONENT_FLOAT: u64 = 5126;
+const MODE_TRIANGLES: u64 = 4;
+
+// アクセサの要素数の上限。壊れたファイルで巨大なメモリを確保しないようにする
+const MAX_ACCESSOR_COUNT: usize = 1 << 24;
+
+// .gltf (外部 .bin / data URI) と .glb を読み込む
+// ノードの変換は頂点に適用し、primitive ごとに 1 つのサブメッシュにする
+pub fn read_gltf<P: AsRef<Path>>(path: P) -> Result<Vec<ImportSubmesh>, ImportError> {
+    let path = path.as_ref();
+    parse_gltf(&fs::read(path)?, path.parent().unwrap_or(Path::new("")))
+}
+
+// 外部バッファの uri は base_dir からの相対パスとして読む
+pub fn parse_gltf(bytes: &[u8], base_dir: &Path) -> Result<Vec<ImportSubmesh>, ImportError> {
+    let (json, bin) = if bytes.starts_with(GLB_MAGIC) {
+        parse_glb(bytes)?
+    } else {
+        (serde_json::from_slice(bytes)?, None)
+    };
+
+    let buffers = json["buffers"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .map(|(i, buffer)| load_buffer(buffer, i, bin.as_deref(), base_dir))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let document = Document {
+        json: &json,
+        buffers,
+    };
+    let mut submeshes = Vec::new();
+
+    let scene = &json["scenes"][json["scene"].as_u64().unwrap_or(0) as usize];
+    match scene["nodes"].as_array() {
+        Some(nodes) => {
+            for node in nodes {
+                document.visit_node(as_index(node)?, Mat4::IDENTITY, 0, &mut submeshes)?;
+            }
+        }
+        // シーンが無いファイルはメッシュをそのまま並べる
+        None => {
+            for mesh in 0..json["meshes"].as_array().map_or(0, Vec::len) {
+                document.add_mesh(mesh, "", Mat4::IDENTITY, &mut submeshes)?;
+            }
+        }
+    }
+
+    Ok(submeshes)
+}
+
+pub fn parse_glb(bytes: &[u8]) -> Result<(Value, Option<Vec<u8>>), ImportError> {
+    let read_u32 = |offset: usize| -> Result<u32, ImportError> {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| ImportError::Parse("GLB file is truncated.".to_string()))
+    };
+
+    let total_length = (read_u32(8)? as usize).min(bytes.len());
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset + 8 <= total_length {
+        let length = read_u32(offset)? as usize;
+        let chunk_type = read_u32(offset + 4)?;
+        let data = bytes
+            .get(offset + 8..offset + 8 + length)
+            .ok_or_else(|| ImportError::Parse("GLB chunk is truncated.".to_string()))?;
+        match chunk_type {
+            GLB_CHUNK_JSON => json = Some(serde_json::from_slice(data)?),
+            GLB_CHUNK_BIN if bin.is_none() => bin = Some(data.to_vec()),
+            _ => {}
+        }
+        offset += 8 + length;
+    }
+
+    let json = json.ok_or_else(|| ImportError::Parse("GLB has no JSON chunk.".to_string()))?;
+    Ok((json, bin))
+}
+
+fn load_buffer(
+    buffer: &Value,
+    index: usize,
+    bin: Option<&[u8]>,
+    base_dir: &Path,
+) -> Result<Vec<u8>, ImportError> {
+    match buffer["uri"].as_str() {
+        Some(uri) if uri.starts_with("data:") => {
+            let (_, data) = uri.split_once(";base64,").ok_or_else(|| {
+                ImportError::Parse(format!("Buffer {} has an unsupported data URI.", index))
+            })?;
+            base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .map_err(|err| ImportError::Parse(format!("Buffer {}: {}", index, err)))
+        }
+        Some(uri) => Ok(fs::read(base_dir.join(uri))?),
+        None => bin.map(<[u8]>::to_vec).ok_or_else(|| {
+            ImportError::Parse(format!(
+                "Buffer {} has no uri and no GLB binary chunk.",
+                index
+            ))
+        }),
+    }
+}
+
+fn as_index(value: &Value) -> Result<usize, ImportError> {
+    value
+        .as_u64()
+        .map(|i| i as usize)
+        .ok_or_else(|| ImportError::Parse(format!("Invalid index: {}", value)))
+}
+
+struct Document<'a> {
+    json: &'a Value,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Document<'_> {
+    fn visit_node(
+        &self,
+        index: usize,
+        parent: Mat4,
+        depth: usize,
+        submeshes: &mut Vec<ImportSubmesh>,
+    ) -> Result<(), ImportError> {
+        // 循環参照しているファイルで無限に再帰しないようにする
+        if depth > 64 {
+            return Err(ImportError::Parse(
+                "Node hierarchy is too deep.".to_string(),
+            ));
+        }
+        let node = &self.json["nodes"][index];
+        let transform = parent * node_transform(node);
+
+        if let Some(mesh) = node.get("mesh") {
+            let name = node["name"].as_str().unwrap_or_default();
+            self.add_mesh(as_index(mesh)?, name, transform, submeshes)?;
+        }
+        for child in node["children"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            self.visit_node(as_index(child)?, transform, depth + 1, submeshes)?;
+        }
+        Ok(())
+    }
+
+    fn add_mesh(
+        &self,
+        index: usize,
+        node_name: &str,
+        transform: Mat4,
+        submeshes: &mut Vec<ImportSubmesh>,
+    ) -> Result<(), ImportError> {
+        let mesh = &self.json["meshes"][index];
+        let mesh_name = mesh["name"].as_str().unwrap_or(node_name);
+        let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+        // 鏡像変換では三角形の向きが反転する
+        let mirrored = transform.determinant() < 0.0;
+
+        for (i, primitive) in mesh["primitives"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+        {
+            if primitive["mode"].as_u64().unwrap_or(MODE_TRIANGLES) != MODE_TRIANGLES {
+                return Err(ImportError::Parse(format!(
+                    "Mesh \"{}\" primitive {} is not made of triangles.",
+                    mesh_name, i
+                )));
+            }
+            let attributes = &primitive["attributes"];
+
+            let positions = match attributes.get("POSITION") {
+                Some(accessor) => self.read_typed_accessor(accessor, &["VEC3"], false)?,
+                None => continue,
+            };
+            let positions: Vec<Vec3> = positions
+                .chunks_exact(3)
+                .map(|p| {
+                    transform.transform_point3(Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32))
+                })
+                .collect();
+
+            let mut indices = match primitive.get("indices") {
+                Some(accessor) => self
+                    .read_typed_accessor(accessor, &["SCALAR"], false)?
+                    .into_iter()
+                    .map(|i| i as u32)
+                    .collect(),
+                None => (0..positions.len() as u32).collect::<Vec<_>>(),
+            };
+            if mirrored {
+                for triangle in indices.chunks_exact_mut(3) {
+                    triangle.swap(1, 2);
+                }
+            }
+
+            let normals: Vec<Vec3> = match attributes.get("NORMAL") {
+                Some(accessor) => self
+                    .read_typed_accessor(accessor, &["VEC3"], false)?
+                    .chunks_exact(3)
+                    .map(|n| {
+                        (normal_matrix * Vec3::new(n[0] as f32, n[1] as f32, n[2] as f32))
+                            .normalize_or_zero()
+                    })
+                    .collect(),
+                None => smooth_normals(&positions, &indices),
+            };
+
+            let colors: Vec<Color4> = match attributes.get("COLOR_0") {
+                Some(accessor) => {
+                    let components =
+                        type_components(&self.json["accessors"][as_index(accessor)?]["type"]);
+                    self.read_typed_accessor(accessor, &["VEC3", "VEC4"], true)?
+                        .chunks_exact(components)
+                        // COLOR_0 はリニアなのでメッシュの sRGB に戻す
+                        .map(|c| {
+                            Color4 {
+                                r: c[0] as f32,
+                                g: c[1] as f32,
+                                b: c[2] as f32,
+                                a: c.get(3).map_or(1.0, |a| *a as f32),
+                            }
+                            .to_srgb()
+                        })
+                        .collect()
+                }
+                None => vec![Color4::WHITE; positions.len()],
+            };
+
+            if normals.len() != positions.len() || colors.len() != positions.len() {
+                return Err(ImportError::Parse(format!(
+                    "Mesh \"{}\" primitive {} has attributes of different lengths.",
+                    mesh_name, i
+                )));
+            }
+
+            let extras = &primitive["extras"];
+            let name = match extras["name"].as_str() {
+                Some(name) => name.to_string(),
+                None if mesh["primitives"].as_array().map_or(0, Vec::len) > 1 => {
+                    format!("{}.{}", mesh_name, i)
+                }
+                None => mesh_name.to_string(),
+            };
+            let shader_id = match extras["shader_id"].as_u64() {
+                Some(id) => id as u16,
+                None => self.material_shader_id(&primitive["material"]),
+            };
+
+            submeshes.push(ImportSubmesh {
+                name,
+                shader_id,
+                vertices: positions
+                    .into_iter()
+                    .zip(normals)
+                    .zip(colors)
+                    .map(|((position, normal), color)| MeshVertex {
+                        position,
+                        color,
+                        normal,
+                    })
+                    .collect(),
+                indices,
+            });
+        }
+        Ok(())
+    }
+
+    fn material_shader_id(&self, material: &Value) -> u16 {
+        let Some(index) = material.as_u64() else {
+            return SHADER_BASIC;
+        };
+        let material = &self.json["materials"][index as usize];
+        match material["name"].as_str().map(shader_id_for_material) {
+            Some(SHADER_BASIC) | None if material["alphaMode"] == "BLEND" => SHADER_GLASS,
+            Some(id) => id,
+            None => SHADER_BASIC,
+        }
+    }
+
+    // 属性ごとに決まった型のアクセサだけを読む
+    fn read_typed_accessor(
+        &self,
+        accessor: &Value,
+        types: &[&str],
+        normalized: bool,
+    ) -> Result<Vec<f64>, ImportError> {
+        let index = as_index(accessor)?;
+        let accessor_type = self.json["accessors"][index]["type"]
+            .as_str()
+            .unwrap_or_default();
+        if !types.contains(&accessor_type) {
+            return Err(ImportError::Parse(format!(
+                "Accessor {}: type {:?} is not {}",
+                index,
+                accessor_type,
+                types.join(" or ")
+            )));
+        }
+        self.read_accessor(index, normalized)
+    }
+
+    // アクセサの値を成分ごとに並べて返す
+    // normalized が true なら整数型を 0.0 から 1.0 (符号付きは -1.0 から 1.0) に変換する
+    fn read_accessor(&self, index: usize, normalized: bool) -> Result<Vec<f64>, ImportError> {
+        let accessor = &self.json["accessors"][index];
+        let error = |message: &str| ImportError::Parse(format!("Accessor {}: {}", index, message));
+
+        if accessor.get("sparse").is_some() {
+            return Err(error("sparse accessors are not supported"));
+        }
+        let count = accessor["count"]
+            .as_u64()
+            .ok_or_else(|| error("no count"))? as usize;
+        let components = type_components(&accessor["type"]);
+        let component_type = accessor["componentType"].as_u64().unwrap_or_default();
+        let component_size = match component_type {
+            COMPONENT_BYTE | COMPONENT_UNSIGNED_BYTE => 1,
+            COMPONENT_SHORT | COMPONENT_UNSIGNED_SHORT => 2,
+            COMPONENT_UNSIGNED_INT | COMPONENT_FLOAT => 4,
+            _ => return Err(error("unsupported component type")),
+        };
+        if components == 0 {
+            return Err(error("unsupported type"));
+        }
+        let len = count
+            .checked_mul(components)
+            .filter(|_| count <= MAX_ACCESSOR_COUNT)
+            .ok_or_else(|| error("count is too large"))?;
+
+        let Some(buffer_view) = accessor.get("bufferView") else {
+            return Ok(vec![0.0; len]);
+        };
+        let buffer_view = &self.json["bufferViews"][as_index(buffer_view)?];
+        let buffer = self
+            .buffers
+            .get(buffer_view["buffer"].as_u64().unwrap_or_default() as usize)
+            .ok_or_else(|| error("buffer not found"))?;
+
+        let offset = buffer_view["byteOffset"].as_u64().unwrap_or_default() as usize
+            + accessor["byteOffset"].as_u64().unwrap_or_default() as usize;
+        let element_size = components * component_size;
+        let stride = buffer_view["byteStride"]
+            .as_u64()
+            .map_or(element_size, |stride| stride as usize);
+
+        let mut values = Vec::with_capacity(len);
+        for i in 0..count {
+            for c in 0..components {
+                let start = offset + i * stride + c * component_size;
+                let bytes = buffer
+                    .get(start..start + component_size)
+                    .ok_or_else(|| error("data is out of the buffer"))?;
+                let value = match component_type {
+                    COMPONENT_BYTE => {
+                        let v = bytes[0] as i8 as f64;
+                        if normalized {
+                            (v / 127.0).max(-1.0)
+                        } else {
+                            v
+                        }
+                    }
+                    COMPONENT_UNSIGNED_BYTE => {
+                        let v = bytes[0] as f64;
+                        if normalized {
+                            v / 255.0
+                        } else {
+                            v
+                        }
+                    }
+                    COMPONENT_SHORT => {
+                        let v = i16::from_le_bytes([bytes[0], bytes[1]]) as f64;
+                        if normalized {
+                            (v / 32767.0).max(-1.0)
+                        } else {
+                            v
+                        }
+                    }
+                    COMPONENT_UNSIGNED_SHORT => {
+                        let v = u16::from_le_bytes([bytes[0], bytes[1]]) as f64;
+                        if normalized {
+                            v / 65535.0
+                        } else {
+                            v
+                        }
+                    }
+                    COMPONENT_UNSIGNED_INT => {
+                        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+                    }
+                    _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                };
+                values.push(value);
+            }
+        }
+        Ok(values)
+    }
+}
+
+fn type_components(accessor_type: &Value) -> usize {
+    match accessor_type.as_str() {
+        Some("SCALAR") => 1,
+        Some("VEC2") => 2,
+        Some("VEC3") => 3,
+        Some("VEC4") => 4,
+        _ => 0,
+    }
+}
+
+fn node_transform(node: &Value) -> Mat4 {
+    let floats = |key: &str| -> Option<Vec<f32>> {
+        node[key]
+            .as_array()?
+            .iter()
+            .map(|v| v.as_f64().map(|v| v as f32))
+            .collect()
+    };
+
+    if let Some(matrix) = floats("matrix").filter(|m| m.len() == 16) {
+        return Mat4::from_cols_slice(&matrix);
+    }
+    let translation = floats("translation")
+        .filter(|t| t.len() == 3)
+        .map_or(Vec3::ZERO, |t| Vec3::from_slice(&t));
+    let rotation = floats("rotation")
+        .filter(|r| r.len() == 4)
+        .map_or(Quat::IDENTITY, |r| Quat::from_slice(&r).normalize());
+    let scale = floats("scale")
+        .filter(|s| s.len() == 3)
+        .map_or(Vec3::ONE, |s| Vec3::from_slice(&s));
+    Mat4::from_scale_rotation_translation(scale, rotation, translation)
+}
+
+// 法線が無いときは隣接する面の法線を面積で重み付けして平均する
+fn smooth_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let (Some(pa), Some(pb), Some(pc)) = (positions.get(a), positions.get(b), positions.get(c))
+        else {
+            continue;
+        };
+        let normal = (*pb - *pa).cross(*pc - *pa);
+        for i in [a, b, c] {
+            normals[i] += normal;
+        }
+    }
+    normals
+        .into_iter()
+        .map(|n| n.try_normalize().unwrap_or(Vec3::Y))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 三角形 1 枚の GLB を作る。COLOR_0 のアクセサの型を color_type にする
+    fn triangle_glb(color_type: &str) -> Vec<u8> {
+        let color_components = type_components(&Value::from(color_type));
+        let mut bin = Vec::new();
+        for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bin.extend(v.to_le_bytes());
+        }
+        for i in [0u16, 1, 2, 0] {
+            bin.extend(i.to_le_bytes());
+        }
+        bin.extend(vec![255; 3 * color_components]);
+        while bin.len() % 4 != 0 {
+            bin.push(0);
+        }
+
+        let json = serde_json::json!({
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0, "translation": [0.0, 0.0, 2.0] }],
+            "meshes": [{
+                "name": "triangle",
+                "primitives": [{ "attributes": { "POSITION": 0, "COLOR_0": 2 }, "indices": 1 }],
+            }],
+            "buffers": [{ "byteLength": bin.len() }],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 6 },
+                { "buffer": 0, "byteOffset": 44, "byteLength": 3 * color_components },
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": COMPONENT_FLOAT, "count": 3, "type": "VEC3" },
+                { "bufferView": 1, "componentType": COMPONENT_UNSIGNED_SHORT, "count": 3, "type": "SCALAR" },
+                {
+                    "bufferView": 2,
+                    "componentType": COMPONENT_UNSIGNED_BYTE,
+                    "normalized": true,
+                    "count": 3,
+                    "type": color_type,
+                },
+            ],
+        });
+        let mut json = serde_json::to_vec(&json).unwrap();
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+
+        let mut glb = Vec::new();
+        glb.extend(GLB_MAGIC);
+        glb.extend(2u32.to_le_bytes());
+        glb.extend(((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        glb.extend((json.len() as u32).to_le_bytes());
+        glb.extend(GLB_CHUNK_JSON.to_le_bytes());
+        glb.extend(json);
+        glb.extend((bin.len() as u32).to_le_bytes());
+        glb.extend(GLB_CHUNK_BIN.to_le_bytes());
+        glb.extend(bin);
+        glb
+    }
+
+    #[test]
+    fn parses_triangle_glb() {
+        let submeshes = parse_gltf(&triangle_glb("VEC4"), Path::new("")).unwrap();
+        assert_eq!(submeshes.len(), 1);
+
+        let submesh = &submeshes[0];
+        assert_eq!(submesh.name, "triangle");
+        assert_eq!(submesh.shader_id, SHADER_BASIC);
+        assert_eq!(submesh.indices, [0, 1, 2]);
+        // ノードの移動が頂点に適用される
+        assert_eq!(submesh.vertices[1].position, Vec3::new(1.0, 0.0, 2.0));
+        // 法線が無いので面の向きから計算する
+        assert_eq!(submesh.vertices[0].normal, Vec3::Z);
+        assert!((submesh.vertices[2].color.r - 1.0).abs() < 1e-6);
+        assert_eq!(submesh.vertices[2].color.a, 1.0);
+    }
+
+    #[test]
+    fn parses_rgb_colors() {
+        let submeshes = parse_gltf(&triangle_glb("VEC3"), Path::new("")).unwrap();
+        assert_eq!(submeshes[0].vertices.len(), 3);
+    }
+
+    #[test]
+    fn rejects_colors_with_too_few_components() {
+        for color_type in ["SCALAR", "VEC2"] {
+            assert!(matches!(
+                parse_gltf(&triangle_glb(color_type), Path::new("")),
+                Err(ImportError::Parse(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_glb() {
+        let glb = triangle_glb("VEC4");
+        assert!(matches!(
+            parse_gltf(&glb[..glb.len() - 8], Path::new("")),
+            Err(ImportError::Parse(_))
+        ));
+        assert!(matches!(parse_glb(&glb[..6]), Err(ImportError::Parse(_))));
+    }
+
+    #[test]
+    fn rejects_too_large_accessor_without_buffer_view() {
+        for count in [u64::MAX, MAX_ACCESSOR_COUNT as u64 + 1] {
+            let gltf = serde_json::json!({
+                "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 } }] }],
+                "accessors": [{ "componentType": 5126, "count": count, "type": "VEC3" }],
+            });
+            assert!(matches!(
+                parse_gltf(gltf.to_string().as_bytes(), Path::new("")),
+                Err(ImportError::Parse(_))
+            ));
+        }
+    }
+}
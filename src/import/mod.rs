@@ -0,0 +1,181 @@
+mod gltf;
+pub use gltf::{parse_glb, parse_gltf, read_gltf};
+mod obj;
+pub use obj::{parse_obj, read_obj};
+
+use crate::gl_renderer::MeshVertex;
+use crate::sw_block_definition::{
+    SwMesh, SwMeshFromFileError, SwMeshTriangle, SwMeshType, SwMeshVertex, SwSubmesh,
+};
+use glam::Vec3;
+use std::{collections::HashMap, fmt, io, path::Path};
+
+const SHADER_BASIC: u16 = 0;
+const SHADER_GLASS: u16 = 1;
+
+// 読み込んだモデルのサブメッシュ
+// 座標はビューアと同じ右手系で、書き出すときに左手系に戻す
+pub struct ImportSubmesh {
+    pub name: String,
+    pub shader_id: u16,
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl ImportSubmesh {
+    fn new(name: String, shader_id: u16) -> Self {
+        Self {
+            name,
+            shader_id,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Parse(String),
+    Mesh(SwMeshFromFileError),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => err.fmt(f),
+            Self::Json(err) => err.fmt(f),
+            Self::Parse(message) => write!(f, "{}", message),
+            Self::Mesh(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(err: io::Error) -> Self {
+        ImportError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(err: serde_json::Error) -> Self {
+        ImportError::Json(err)
+    }
+}
+
+impl From<SwMeshFromFileError> for ImportError {
+    fn from(err: SwMeshFromFileError) -> Self {
+        ImportError::Mesh(err)
+    }
+}
+
+// 拡張子から形式を判断して .obj / .gltf / .glb を読み込む
+pub fn import_mesh<P: AsRef<Path>>(path: P, mesh_type: SwMeshType) -> Result<SwMesh, ImportError> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    let submeshes = match extension.as_deref() {
+        Some("obj") => read_obj(path)?,
+        Some("gltf") | Some("glb") => read_gltf(path)?,
+        _ => {
+            return Err(ImportError::Parse(format!(
+                "Unsupported file type: {}",
+                path.display()
+            )))
+        }
+    };
+    build_sw_mesh(mesh_type, &submeshes)
+}
+
+// 全サブメッシュで頂点バッファを共有し、同じ値の頂点はまとめる
+// 頂点数とインデックスは u16 なので 65535 頂点を超えるモデルは書き出せない
+pub fn build_sw_mesh(
+    mesh_type: SwMeshType,
+    submeshes: &[ImportSubmesh],
+) -> Result<SwMesh, ImportError> {
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+    let mut sw_submeshes = Vec::new();
+    let mut vertex_indices: HashMap<[u32; 10], u16> = HashMap::new();
+
+    for submesh in submeshes.iter().filter(|s| !s.indices.is_empty()) {
+        if submesh.indices.len() % 3 != 0 {
+            return Err(ImportError::Parse(format!(
+                "Submesh \"{}\" is not triangulated: index count {} is not a multiple of 3.",
+                submesh.name,
+                submesh.indices.len()
+            )));
+        }
+
+        let start = triangles.len();
+        let mut bounds_min = Vec3::INFINITY;
+        let mut bounds_max = Vec3::NEG_INFINITY;
+
+        for indices in submesh.indices.chunks_exact(3) {
+            let mut triangle = [0; 3];
+            for (t, i) in triangle.iter_mut().zip(indices) {
+                let vertex = submesh.vertices.get(*i as usize).ok_or_else(|| {
+                    ImportError::Parse(format!(
+                        "Submesh \"{}\" refers to vertex {} which does not exist.",
+                        submesh.name, i
+                    ))
+                })?;
+                let sw_vertex = SwMeshVertex::from_mesh_vertex(vertex);
+                bounds_min = bounds_min.min(sw_vertex.position());
+                bounds_max = bounds_max.max(sw_vertex.position());
+
+                let key = vertex_key(vertex);
+                *t = match vertex_indices.get(&key) {
+                    Some(index) => *index,
+                    None => {
+                        if vertices.len() >= u16::MAX as usize {
+                            return Err(ImportError::Parse(format!(
+                                "Too many vertices: a mesh file can hold at most {} unique vertices, exceeded in submesh \"{}\". Split the model into several meshes.",
+                                u16::MAX,
+                                submesh.name
+                            )));
+                        }
+                        let index = vertices.len() as u16;
+                        vertices.push(sw_vertex);
+                        vertex_indices.insert(key, index);
+                        index
+                    }
+                };
+            }
+            triangles.push(SwMeshTriangle::new(triangle));
+        }
+
+        sw_submeshes.push(SwSubmesh::new(
+            start..triangles.len(),
+            submesh.shader_id,
+            bounds_min,
+            bounds_max,
+            submesh.name.clone(),
+        )?);
+    }
+
+    Ok(SwMesh::new(mesh_type, vertices, triangles, sw_submeshes)?)
+}
+
+fn vertex_key(vertex: &MeshVertex) -> [u32; 10] {
+    let p = vertex.position;
+    let n = vertex.normal;
+    let c = &vertex.color;
+    [p.x, p.y, p.z, n.x, n.y, n.z, c.r, c.g, c.b, c.a].map(f32::to_bits)
+}
+
+// マテリアル名からシェーダーを決める
+// "glass" で始まる名前はガラス、"shader_<番号>" は番号をそのまま使う
+fn shader_id_for_material(name: &str) -> u16 {
+    let name = name.to_ascii_lowercase();
+    if name.starts_with("glass") {
+        SHADER_GLASS
+    } else if let Some(id) = name.strip_prefix("shader_").and_then(|id| id.parse().ok()) {
+        id
+    } else {
+        SHADER_BASIC
+    }
+}
@@ -0,0 +1,183 @@
+use super::{shader_id_for_material, ImportError, ImportSubmesh, SHADER_BASIC};
+use crate::gl_renderer::{Color4, MeshVertex};
+use glam::Vec3;
+use std::{fs, path::Path};
+
+pub fn read_obj<P: AsRef<Path>>(path: P) -> Result<Vec<ImportSubmesh>, ImportError> {
+    parse_obj(&fs::read_to_string(path)?)
+}
+
+// g / o / usemtl ごとにサブメッシュを分ける
+// 頂点カラーは "v x y z r g b" 形式に対応する
+pub fn parse_obj(text: &str) -> Result<Vec<ImportSubmesh>, ImportError> {
+    let mut positions: Vec<(Vec3, Color4)> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut submeshes: Vec<ImportSubmesh> = Vec::new();
+
+    let mut name = String::new();
+    let mut shader_id = SHADER_BASIC;
+    let mut new_submesh = true;
+
+    for (line_number, line) in text.lines().enumerate() {
+        let parse_error = |message: &str| {
+            ImportError::Parse(format!("OBJ line {}: {}", line_number + 1, message))
+        };
+
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let values = parse_floats(&args).ok_or_else(|| parse_error("invalid vertex"))?;
+                let color = match values.as_slice() {
+                    [_, _, _, r, g, b, ..] => Color4 {
+                        r: *r,
+                        g: *g,
+                        b: *b,
+                        a: 1.0,
+                    },
+                    [_, _, _] | [_, _, _, _] => Color4::WHITE,
+                    _ => return Err(parse_error("invalid vertex")),
+                };
+                positions.push((Vec3::new(values[0], values[1], values[2]), color));
+            }
+            "vn" => match parse_floats(&args).as_deref() {
+                Some([x, y, z]) => normals.push(Vec3::new(*x, *y, *z).normalize_or_zero()),
+                _ => return Err(parse_error("invalid normal")),
+            },
+            "g" | "o" => {
+                name = args.join(" ");
+                new_submesh = true;
+            }
+            "usemtl" => {
+                shader_id = shader_id_for_material(&args.join(" "));
+                new_submesh = true;
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(parse_error("face has less than 3 vertices"));
+                }
+                let corners = args
+                    .iter()
+                    .map(|arg| parse_corner(arg, positions.len(), normals.len()))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| parse_error("invalid face"))?;
+
+                // 法線が無い面は面の向きから計算する
+                let p = |i: usize| positions[corners[i].0].0;
+                let face_normal = (p(1) - p(0)).cross(p(2) - p(0)).normalize_or_zero();
+
+                if new_submesh || submeshes.is_empty() {
+                    submeshes.push(ImportSubmesh::new(name.clone(), shader_id));
+                    new_submesh = false;
+                }
+                let submesh = submeshes.last_mut().unwrap();
+
+                let base = submesh.vertices.len() as u32;
+                for (position, normal) in &corners {
+                    let (position, color) = &positions[*position];
+                    submesh.vertices.push(MeshVertex {
+                        position: *position,
+                        color: *color,
+                        normal: normal.map_or(face_normal, |i| normals[i]),
+                    });
+                }
+                // 多角形は扇形に三角形分割する
+                for i in 1..(corners.len() as u32 - 1) {
+                    submesh.indices.extend([base, base + i, base + i + 1]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(submeshes)
+}
+
+fn parse_floats(args: &[&str]) -> Option<Vec<f32>> {
+    args.iter().map(|arg| arg.parse().ok()).collect()
+}
+
+// "v", "v/vt", "v//vn", "v/vt/vn" を 0 始まりの (頂点, 法線) に変換する
+fn parse_corner(
+    arg: &str,
+    position_count: usize,
+    normal_count: usize,
+) -> Option<(usize, Option<usize>)> {
+    let mut parts = arg.split('/');
+    let position = resolve_index(parts.next()?, position_count)?;
+    let normal = match parts.nth(1) {
+        Some(normal) if !normal.is_empty() => Some(resolve_index(normal, normal_count)?),
+        _ => None,
+    };
+    Some((position, normal))
+}
+
+// OBJ のインデックスは 1 始まりで、負の値は末尾からの相対位置
+fn resolve_index(s: &str, count: usize) -> Option<usize> {
+    let index: isize = s.parse().ok()?;
+    let index = if index < 0 {
+        count.checked_sub(index.unsigned_abs())?
+    } else {
+        (index as usize).checked_sub(1)?
+    };
+    (index < count).then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::SHADER_GLASS;
+
+    const QUAD: &str = "\
+# comment
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0 1 0 0
+vn 0 0 -1
+g panel
+f 1 2 3 4
+usemtl glass_window
+f -4//1 -2//1 -1//1
+";
+
+    #[test]
+    fn parses_groups_materials_and_colors() {
+        let submeshes = parse_obj(QUAD).unwrap();
+        assert_eq!(submeshes.len(), 2);
+
+        let quad = &submeshes[0];
+        assert_eq!(quad.name, "panel");
+        assert_eq!(quad.shader_id, SHADER_BASIC);
+        assert_eq!(quad.vertices.len(), 4);
+        assert_eq!(quad.indices, [0, 1, 2, 0, 2, 3]);
+        // 法線の無い面は面の向きから計算する
+        assert_eq!(quad.vertices[0].normal, Vec3::Z);
+        assert_eq!(quad.vertices[3].color.r, 1.0);
+        assert_eq!(quad.vertices[3].color.g, 0.0);
+
+        let glass = &submeshes[1];
+        assert_eq!(glass.shader_id, SHADER_GLASS);
+        assert_eq!(glass.indices, [0, 1, 2]);
+        assert_eq!(glass.vertices[0].position, Vec3::ZERO);
+        assert_eq!(glass.vertices[2].position, Vec3::Y);
+        assert_eq!(glass.vertices[1].normal, Vec3::NEG_Z);
+    }
+
+    #[test]
+    fn reports_line_of_invalid_face() {
+        let Err(ImportError::Parse(message)) = parse_obj("v 0 0 0\nf 1 2 3\n") else {
+            panic!("face with missing vertices was accepted");
+        };
+        assert!(message.contains("line 2"), "{}", message);
+    }
+
+    #[test]
+    fn rejects_invalid_vertex() {
+        assert!(matches!(parse_obj("v 0 0\n"), Err(ImportError::Parse(_))));
+    }
+}
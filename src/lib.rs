@@ -1,5 +1,8 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+pub mod export;
 mod gl_renderer;
-mod sw_block_definition;
+pub mod import;
+pub mod sw_block_definition;
+pub mod thumbnail;
 pub mod ui;
@@ -0,0 +1,389 @@
+use std::fmt;
+
+// 数値で書かれた属性の値に名前を付ける
+// 知らない値も Unknown(i32) として失わずに扱う
+// guessed に並べた名前は出典の無い推定で、表示では "Wedge?" のように ? を付ける
+macro_rules! attribute_enum {
+    (
+        $name:ident {
+            $($value:literal => $variant:ident: $label:literal),* $(,)?
+        }
+        $(guessed {
+            $($guessed_value:literal => $guessed_variant:ident: $guessed_label:literal),* $(,)?
+        })?
+    ) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($variant,)*
+            $($($guessed_variant,)*)?
+            Unknown(i32),
+        }
+
+        impl $name {
+            // 名前の分かっている値 (推定を含む)
+            pub const KNOWN: &'static [Self] =
+                &[$(Self::$variant,)* $($(Self::$guessed_variant,)*)?];
+
+            pub fn from_i32(value: i32) -> Self {
+                match value {
+                    $($value => Self::$variant,)*
+                    $($($guessed_value => Self::$guessed_variant,)*)?
+                    _ => Self::Unknown(value),
+                }
+            }
+
+            pub fn from_attribute(value: &Option<i32>) -> Option<Self> {
+                value.map(Self::from_i32)
+            }
+
+            pub fn to_i32(self) -> i32 {
+                match self {
+                    $(Self::$variant => $value,)*
+                    $($(Self::$guessed_variant => $guessed_value,)*)?
+                    Self::Unknown(value) => value,
+                }
+            }
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $label,)*
+                    $($(Self::$guessed_variant => $guessed_label,)*)?
+                    Self::Unknown(_) => "Unknown",
+                }
+            }
+
+            pub fn is_known(self) -> bool {
+                !matches!(self, Self::Unknown(_))
+            }
+
+            // 名前が出典の無い推定か
+            pub fn is_guessed(self) -> bool {
+                match self {
+                    $($(Self::$guessed_variant => true,)*)?
+                    _ => false,
+                }
+            }
+
+            // 表示用の名前。推定なら ? を付ける
+            pub fn label(self) -> String {
+                if self.is_guessed() {
+                    format!("{}?", self.name())
+                } else {
+                    self.name().to_string()
+                }
+            }
+        }
+
+        impl From<i32> for $name {
+            fn from(value: i32) -> Self {
+                Self::from_i32(value)
+            }
+        }
+
+        impl From<$name> for i32 {
+            fn from(value: $name) -> Self {
+                value.to_i32()
+            }
+        }
+
+        // "3 (Vehicle Control)" または "1 (Wedge?)" の形で表示する
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{} ({})", self.to_i32(), self.label())
+            }
+        }
+    };
+}
+
+// definition の属性名から、値の名前と名前の分かっている値の一覧を引く
+// unnamed には値が種類を表すが名前の表が無い属性を並べる
+macro_rules! attribute_enum_table {
+    (
+        named: { $($attribute:literal => $name:ident),* $(,)? },
+        unnamed: [ $($unnamed:literal),* $(,)? ] $(,)?
+    ) => {
+        // 名前の表がある属性
+        pub const NAMED_CODED_ATTRIBUTES: &[&str] = &[$($attribute),*];
+
+        // 値が種類を表すが、名前の表が無いので数値のまま表示する属性
+        pub const UNNAMED_CODED_ATTRIBUTES: &[&str] = &[$($unnamed),*];
+
+        // 名前の付いた値を持つ属性なら "3 (Vehicle Control)" 形式の文字列を返す
+        pub fn attribute_value_label(attribute: &str, value: i32) -> Option<String> {
+            match attribute {
+                $($attribute => Some($name::from_i32(value).to_string()),)*
+                _ => None,
+            }
+        }
+
+        // 名前の分かっている (値, 表示用の名前) の一覧
+        pub fn attribute_value_names(attribute: &str) -> Option<Vec<(i32, String)>> {
+            match attribute {
+                $($attribute => Some(
+                    $name::KNOWN.iter().map(|v| (v.to_i32(), v.label())).collect()
+                ),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+// 値と名前の対応の出典について
+// 定義ファイルには数値しか書かれておらず、対応を記した公式の資料は無い
+// guessed の外に置くのは出典のある対応だけにする
+//   Category 3 = Vehicle Control: 要望 (user-021) に例として挙がっている対応
+// guessed の名前はゲームのデータと照合していない推定で、表示では ? を付ける
+// 名前の誤りは表示にしか影響しない (値は数値のまま読み書きする)
+// 表に無い値は Unknown になる。ROM の値がすべて表に載っているかは
+// tests::rom_values_are_known (SW_ROM_PATH を指定して --ignored で実行) で調べられる
+
+// 部品メニューの並び順からの推定は 3 の対応と食い違ったので載せていない
+attribute_enum!(Category {
+    3 => VehicleControl: "Vehicle Control",
+});
+
+attribute_enum!(DefinitionType {} guessed {
+    0 => Block: "Block",
+    1 => Seat: "Seat",
+    2 => Wheel: "Wheel",
+    3 => Button: "Button",
+    4 => Propeller: "Propeller",
+    5 => Rudder: "Rudder",
+    6 => Sensor: "Sensor",
+    7 => Light: "Light",
+    8 => Hinge: "Hinge",
+    9 => Door: "Door",
+    10 => Indicator: "Indicator",
+    11 => LogicGate: "Logic Gate",
+    12 => Connector: "Connector",
+    13 => Gyro: "Gyro",
+    14 => Rope: "Rope",
+    15 => Handle: "Handle",
+});
+
+attribute_enum!(BlockType {} guessed {
+    0 => Normal: "Normal",
+    1 => Wedge: "Wedge",
+    2 => Pyramid: "Pyramid",
+    3 => InvPyramid: "Inverse Pyramid",
+});
+
+attribute_enum!(ConstraintType {} guessed {
+    0 => None: "None",
+    1 => Hinge: "Hinge",
+    2 => Ball: "Ball",
+    3 => Slider: "Slider",
+    4 => Fixed: "Fixed",
+});
+
+attribute_enum!(SeatType {} guessed {
+    0 => Standard: "Standard",
+    1 => Pilot: "Pilot",
+    2 => Bed: "Bed",
+    3 => Stretcher: "Stretcher",
+});
+
+attribute_enum!(LightType {} guessed {
+    0 => Point: "Point",
+    1 => Spot: "Spot",
+    2 => Indicator: "Indicator",
+});
+
+attribute_enum!(LogicGateType {} guessed {
+    0 => And: "AND",
+    1 => Or: "OR",
+    2 => Xor: "XOR",
+    3 => Nand: "NAND",
+    4 => Nor: "NOR",
+    5 => Not: "NOT",
+    6 => PushToToggle: "Push To Toggle",
+    7 => SrLatch: "SR Latch",
+    8 => JkFlipFlop: "JK Flip Flop",
+    9 => Capacitor: "Capacitor",
+    10 => Blinker: "Blinker",
+    11 => TimerTon: "Timer (TON)",
+    12 => TimerTof: "Timer (TOF)",
+    13 => TimerRto: "Timer (RTO)",
+    14 => TimerRtf: "Timer (RTF)",
+    15 => UpDownCounter: "Up/Down Counter",
+    16 => Threshold: "Threshold",
+    17 => NumericalSwitchbox: "Numerical Switchbox",
+});
+
+attribute_enum!(WeaponType {} guessed {
+    0 => MachineGun: "Machine Gun",
+    1 => LightAutocannon: "Light Auto Cannon",
+    2 => RotaryAutocannon: "Rotary Auto Cannon",
+    3 => HeavyAutocannon: "Heavy Auto Cannon",
+    4 => BattleCannon: "Battle Cannon",
+    5 => ArtilleryCannon: "Artillery Cannon",
+    6 => BigBertha: "Bertha Cannon",
+    7 => RocketLauncher: "Rocket Launcher",
+});
+
+attribute_enum!(ElectricType {} guessed {
+    0 => None: "None",
+    1 => Battery: "Battery",
+    2 => Generator: "Generator",
+    3 => SolarPanel: "Solar Panel",
+    4 => Motor: "Electric Motor",
+    5 => Relay: "Relay",
+    6 => Switchbox: "Switchbox",
+});
+
+// logic_node の type
+attribute_enum!(LogicNodeType {} guessed {
+    0 => OnOff: "On/Off",
+    1 => Number: "Number",
+    2 => Power: "Power",
+    3 => Fluid: "Fluid",
+    4 => Electric: "Electric",
+    5 => Composite: "Composite",
+    6 => Video: "Video",
+    7 => Audio: "Audio",
+    8 => Rope: "Rope",
+});
+
+// logic_node の mode
+attribute_enum!(LogicNodeMode {} guessed {
+    0 => Output: "Output",
+    1 => Input: "Input",
+});
+
+// 子要素の種類を表す属性は別の場所で扱う
+//   surface の shape          surface_mesh.rs の形の表
+//   orientation               marker.rs の orientation_direction
+//   coupling の coupling_gender marker.rs の coupling_gender_name
+attribute_enum_table!(
+    named: {
+        "category" => Category,
+        "type" => DefinitionType,
+        "block_type" => BlockType,
+        "constraint_type" => ConstraintType,
+        "seat_type" => SeatType,
+        "light_type" => LightType,
+        "logic_gate_type" => LogicGateType,
+        "weapon_type" => WeaponType,
+        "electric_type" => ElectricType,
+    },
+    // 名前を確かめられる資料が無いもの
+    unnamed: [
+        "constraint_axis",
+        "seat_pose",
+        "trans_conn_type",
+        "trans_type",
+        "button_type",
+        "custom_door_type",
+        "logic_gate_subtype",
+        "indicator_type",
+        "connector_type",
+        "gyro_type",
+        "reward_tier",
+        "torque_component_type",
+        "jet_engine_component_type",
+        "inventory_class",
+        "inventory_default_item",
+        "composite_type",
+        "weapon_class",
+        "weapon_belt_type",
+        "rocket_type",
+        "engine_module_type",
+        "steam_component_type",
+        "nuclear_component_type",
+        "radar_type",
+        "data_logger_component_type",
+        "metadata_component_type",
+        "oil_component_type",
+        "tool_type",
+    ],
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sw_block_definition::definition_schema::{AttributeValue, Definition};
+
+    // 種類ではなく量を表す整数の属性
+    const NUMERIC_ATTRIBUTES: &[&str] = &[
+        "seat_health_per_sec",
+        "door_side_dist",
+        "door_up_dist",
+        "revision",
+        "electric_charge_capacity",
+        "weapon_ammo_capacity",
+        "weapon_barrel_length_voxels",
+    ];
+
+    #[test]
+    fn every_integer_attribute_is_classified() {
+        let unclassified: Vec<&str> = Definition::default()
+            .attributes()
+            .into_iter()
+            .filter(|(_, value)| matches!(value, AttributeValue::I32(_)))
+            .map(|(name, _)| name)
+            .filter(|name| {
+                !NAMED_CODED_ATTRIBUTES.contains(name)
+                    && !UNNAMED_CODED_ATTRIBUTES.contains(name)
+                    && !NUMERIC_ATTRIBUTES.contains(name)
+            })
+            .collect();
+        assert!(unclassified.is_empty(), "{:?}", unclassified);
+    }
+
+    #[test]
+    fn labels_known_and_unknown_values() {
+        assert_eq!(
+            attribute_value_label("category", 3).as_deref(),
+            Some("3 (Vehicle Control)")
+        );
+        assert_eq!(
+            attribute_value_label("category", 99).as_deref(),
+            Some("99 (Unknown)")
+        );
+        // 推定の名前には ? を付ける
+        assert_eq!(
+            attribute_value_label("block_type", 1).as_deref(),
+            Some("1 (Wedge?)")
+        );
+        assert!(BlockType::Wedge.is_guessed());
+        assert!(!Category::VehicleControl.is_guessed());
+        assert_eq!(attribute_value_label("seat_pose", 1), None);
+        assert_eq!(Category::from_i32(99).to_i32(), 99);
+    }
+
+    // cargo test -- --ignored rom_values_are_known に SW_ROM_PATH で ROM フォルダを渡す
+    // 表に無い値を属性ごとにまとめて報告する
+    #[test]
+    #[ignore]
+    fn rom_values_are_known() {
+        let rom_path = std::env::var("SW_ROM_PATH").expect("SW_ROM_PATH is not set");
+        let definitions_path = std::path::Path::new(&rom_path)
+            .join("data")
+            .join("definitions");
+        let mut unknown = std::collections::BTreeMap::<&str, Vec<String>>::new();
+        for entry in std::fs::read_dir(definitions_path).unwrap() {
+            let path = entry.unwrap().path();
+            if !path.extension().is_some_and(|ext| ext == "xml") {
+                continue;
+            }
+            let Ok(data) = Definition::from_xml(&std::fs::read_to_string(&path).unwrap()) else {
+                continue;
+            };
+            for (name, value) in data.attributes() {
+                let AttributeValue::I32(Some(value)) = value else {
+                    continue;
+                };
+                if let Some(label) = attribute_value_label(name, *value) {
+                    if label.ends_with("(Unknown)") {
+                        let filename = path.file_name().unwrap().to_string_lossy();
+                        unknown
+                            .entry(name)
+                            .or_default()
+                            .push(format!("{}={}", filename, value));
+                    }
+                }
+            }
+        }
+        assert!(unknown.is_empty(), "{:#?}", unknown);
+    }
+}
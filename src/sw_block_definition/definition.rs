@@ -1,23 +1,41 @@
 use super::{
-    definition_schema::Definition,
+    definition_schema::{AttributeValue, Definition, OwnedAttributeValue},
     sw_mesh::{SwMesh, SwMeshFromFileError},
+    xml_element::{XmlReadError, XmlWriteError},
 };
 use std::{
     collections::BTreeMap,
     fmt, io,
     path::{Path, PathBuf},
-    rc::Rc,
+    sync::Arc,
+    time::SystemTime,
 };
 
+pub type LoadedDefinition = Result<Arc<Definition>, SwBlockDefinitionDataError>;
+
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct SwBlockDefinition {
     rom_path: PathBuf,
     path: PathBuf,
     filename: String,
     #[serde(skip)]
-    data: Option<Result<Rc<Definition>, SwBlockDefinitionDataError>>,
+    data: Option<Result<Arc<Definition>, SwBlockDefinitionDataError>>,
+    #[serde(skip)]
+    meshes: Option<Arc<SwBlockDefinitionMeshes>>,
+    #[serde(skip)]
+    history: EditHistory,
+    // 最後に読み込みまたは保存した時点のファイルの更新日時
+    #[serde(skip)]
+    modified: Option<SystemTime>,
+    // 編集中にファイルが外で書き換えられた、または削除された
     #[serde(skip)]
-    meshes: Option<Rc<SwBlockDefinitionMeshes>>,
+    disk_change: Option<DiskChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskChange {
+    Modified,
+    Deleted,
 }
 
 impl SwBlockDefinition {
@@ -30,6 +48,9 @@ impl SwBlockDefinition {
             filename,
             data: None,
             meshes: Default::default(),
+            history: Default::default(),
+            modified: None,
+            disk_change: None,
         })
     }
 
@@ -37,56 +58,276 @@ impl SwBlockDefinition {
         self.filename.clone()
     }
 
-    fn open_file(&mut self) -> Result<Rc<Definition>, SwBlockDefinitionDataError> {
-        let xml = std::fs::read_to_string(self.path.clone())?;
-
-        // ルート要素が  <definition> であるかチェック
-        let is_definition: Result<(), String> = {
-            let mut xml_reader = quick_xml::Reader::from_str(&xml);
-            xml_reader.config_mut().trim_text(true);
-            loop {
-                if let Ok(event) = xml_reader.read_event() {
-                    if let quick_xml::events::Event::Start(ref e) = event {
-                        if e.name().as_ref() == b"definition" {
-                            break Ok(());
-                        } else {
-                            break Err(format!(
-                                "Unexpected root element: {:?}",
-                                std::str::from_utf8(e.name().as_ref()).unwrap_or_default(),
-                            ));
-                        }
-                    }
-                } else {
-                    break Err("Could not find root element".to_string());
-                }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn rom_path(&self) -> &Path {
+        &self.rom_path
+    }
+
+    // 定義ファイルを読み込む。メッシュは使うときに load_meshes で読み込む
+    // self を使わないのでワーカースレッドからも呼べる
+    // 読み込む前に取ったファイルの更新日時も返す
+    pub fn load(path: &Path) -> (LoadedDefinition, Option<SystemTime>) {
+        let modified = file_modified(path);
+        (Self::read(path), modified)
+    }
+
+    fn read(path: &Path) -> LoadedDefinition {
+        let xml = std::fs::read_to_string(path)?;
+        // ルート要素が <definition> でなければ Xml のエラーになる
+        Ok(Arc::new(Definition::from_xml(&xml)?))
+    }
+
+    // data の参照するメッシュを読み込む。ワーカースレッドからも呼べる
+    pub fn load_meshes(data: &Definition, rom_path: &Path) -> Arc<SwBlockDefinitionMeshes> {
+        Arc::new(SwBlockDefinitionMeshes::new(data, rom_path))
+    }
+
+    // load の結果を反映する。読み込み済みなら何もしない
+    // 読み込み中にファイルが書き換えられていたら (更新日時が変わっていたら) 捨てて false を返す
+    pub fn set_loaded(&mut self, loaded: LoadedDefinition, modified: Option<SystemTime>) -> bool {
+        if self.data.is_some() {
+            return true;
+        }
+        if file_modified(&self.path) != modified {
+            return false;
+        }
+        self.modified = modified;
+        self.data = Some(loaded);
+        true
+    }
+
+    // load_meshes の結果を反映する
+    // 読み込み中に定義が書き換えられていたら (data が別物なら) 捨てて false を返す
+    pub fn set_loaded_meshes(
+        &mut self,
+        data: &Arc<Definition>,
+        meshes: Arc<SwBlockDefinitionMeshes>,
+    ) -> bool {
+        match &self.data {
+            Some(Ok(current)) if Arc::ptr_eq(current, data) => {
+                self.meshes = Some(meshes);
+                true
             }
-        };
+            _ => false,
+        }
+    }
 
-        if let Err(mes) = is_definition {
-            Err(SwBlockDefinitionDataError::Xml(mes))
-        } else {
-            let data: Definition = quick_xml::de::from_str(&xml)?;
-            self.meshes = Some(Rc::new(SwBlockDefinitionMeshes::new(
-                &data,
-                self.rom_path.clone(),
-            )));
-            Ok(Rc::new(data))
+    pub fn data(&mut self) -> Result<Arc<Definition>, SwBlockDefinitionDataError> {
+        if self.data.is_none() {
+            // 読み込み中に書き換えられても、更新日時が読む前のものなので is_modified_on_disk でわかる
+            let (loaded, modified) = Self::load(&self.path);
+            self.modified = modified;
+            self.data = Some(loaded);
+        }
+        match self.data.as_ref().unwrap() {
+            Ok(data) => Ok(data.clone()),
+            Err(err) => Err(err.clone()),
         }
     }
 
-    pub fn data(&mut self) -> Result<Rc<Definition>, SwBlockDefinitionDataError> {
-        if let Some(data) = &self.data {
-            data.clone()
-        } else {
-            let data = self.open_file();
-            self.data = Some(data.clone());
-            data
+    // ファイルを読み込み済みか (読み込みに失敗した場合も含む)
+    pub fn is_loaded(&self) -> bool {
+        self.data.is_some()
+    }
+
+    // 読み込み済みのときだけ定義を返す。読み込みはしない
+    pub fn loaded_data(&self) -> Option<Arc<Definition>> {
+        match &self.data {
+            Some(Ok(data)) => Some(data.clone()),
+            _ => None,
         }
     }
 
-    pub fn meshes(&mut self) -> Rc<SwBlockDefinitionMeshes> {
+    // 読み込み済みのときだけメッシュを返す。読み込みはしない
+    pub fn loaded_meshes(&self) -> Option<Arc<SwBlockDefinitionMeshes>> {
+        self.meshes.clone()
+    }
+
+    // 定義は読み込んだがメッシュをまだ読み込んでいないか
+    pub fn needs_meshes(&self) -> bool {
+        matches!(self.data, Some(Ok(_))) && self.meshes.is_none()
+    }
+
+    // 読み込んでいなければその場で読み込む
+    pub fn meshes(&mut self) -> Arc<SwBlockDefinitionMeshes> {
         let _ = self.data();
-        self.meshes.clone().unwrap()
+        if self.meshes.is_none() {
+            self.reload_meshes();
+        }
+        self.meshes.clone().unwrap_or_default()
+    }
+
+    // 定義は残したままメッシュだけ捨ててメモリを空ける
+    pub fn unload_meshes(&mut self) {
+        self.meshes = None;
+    }
+
+    // 読み込んだ内容と履歴を捨て、次に使うときに読み込み直させる
+    pub fn unload(&mut self) {
+        self.data = None;
+        self.meshes = None;
+        self.history = Default::default();
+        self.modified = None;
+        self.disk_change = None;
+    }
+
+    pub fn disk_change(&self) -> Option<DiskChange> {
+        self.disk_change
+    }
+
+    // 編集内容を残したまま、ファイルが外で変更されたことを記録する
+    // 保存するか unload で読み込み直すまで残る
+    pub fn set_disk_change(&mut self, change: DiskChange) {
+        self.disk_change = Some(change);
+    }
+
+    // 読み込みまたは保存した後にファイルが書き換えられたか
+    pub fn is_modified_on_disk(&self) -> bool {
+        file_modified(&self.path) != self.modified
+    }
+
+    // 読み込み済みの定義が path のメッシュを使っているか
+    // ROM フォルダが相対パスでも比べられるように、どちらも正規化する (削除されたファイルはそのまま)
+    pub fn uses_mesh(&self, path: &Path) -> bool {
+        let Some(Ok(data)) = &self.data else {
+            return false;
+        };
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let path = canonical(path);
+        [
+            &data.mesh_data_name,
+            &data.mesh_0_name,
+            &data.mesh_1_name,
+            &data.mesh_2_name,
+            &data.mesh_editor_only_name,
+        ]
+        .into_iter()
+        .flatten()
+        .any(|name| !name.is_empty() && canonical(&self.rom_path.join(name)) == path)
+    }
+
+    // 定義はそのままでメッシュだけ読み込み直す
+    pub fn reload_meshes(&mut self) {
+        if let Some(Ok(data)) = &self.data {
+            self.meshes = Some(Self::load_meshes(data, &self.rom_path));
+        }
+    }
+
+    // 属性を書き換えて履歴に積む
+    // 値が変わらなかったときや属性名と型が合わないときは false を返す
+    pub fn set_attribute(
+        &mut self,
+        name: &str,
+        value: OwnedAttributeValue,
+    ) -> Result<bool, SwBlockDefinitionDataError> {
+        let data = self.data()?;
+        let Some(old) = data.attribute(name).map(AttributeValue::to_owned_value) else {
+            return Ok(false);
+        };
+        if old == value || !self.apply_attribute(name, value.clone()) {
+            return Ok(false);
+        }
+        self.history.push(AttributeEdit {
+            name: name.to_string(),
+            old,
+            new: value,
+        });
+        Ok(true)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.history.redo.is_empty()
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.history.undo.pop() else {
+            return false;
+        };
+        self.apply_attribute(&edit.name, edit.old.clone());
+        self.history.redo.push(edit);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.history.redo.pop() else {
+            return false;
+        };
+        self.apply_attribute(&edit.name, edit.new.clone());
+        self.history.undo.push(edit);
+        true
+    }
+
+    // 最後に読み込みまたは保存した時点から変更されているか
+    pub fn is_dirty(&self) -> bool {
+        self.history.saved != Some(self.history.undo.len())
+    }
+
+    pub fn save(&mut self) -> Result<(), SwBlockDefinitionDataError> {
+        let xml = self.data()?.to_xml()?;
+        std::fs::write(&self.path, xml)?;
+        self.history.saved = Some(self.history.undo.len());
+        self.modified = file_modified(&self.path);
+        self.disk_change = None;
+        Ok(())
+    }
+
+    fn apply_attribute(&mut self, name: &str, value: OwnedAttributeValue) -> bool {
+        let Some(Ok(data)) = &mut self.data else {
+            return false;
+        };
+        if !Arc::make_mut(data).set_attribute(name, value) {
+            return false;
+        }
+        // メッシュのファイル名が変わったら捨てて、次に使うときに読み込み直させる
+        if name.starts_with("mesh_") {
+            self.meshes = None;
+        }
+        true
+    }
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+struct AttributeEdit {
+    name: String,
+    old: OwnedAttributeValue,
+    new: OwnedAttributeValue,
+}
+
+struct EditHistory {
+    undo: Vec<AttributeEdit>,
+    redo: Vec<AttributeEdit>,
+    // 保存した時点での undo の長さ
+    // 元に戻してから別の編集をして保存時点に戻れなくなったら None
+    saved: Option<usize>,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            saved: Some(0),
+        }
+    }
+}
+
+impl EditHistory {
+    fn push(&mut self, edit: AttributeEdit) {
+        if self.saved.is_some_and(|saved| saved > self.undo.len()) {
+            self.saved = None;
+        }
+        self.undo.push(edit);
+        self.redo.clear();
     }
 }
 
@@ -94,6 +335,7 @@ impl SwBlockDefinition {
 pub enum SwBlockDefinitionDataError {
     Io(String),
     De(String),
+    Se(String),
     Xml(String),
 }
 
@@ -103,9 +345,18 @@ impl From<io::Error> for SwBlockDefinitionDataError {
     }
 }
 
-impl From<quick_xml::DeError> for SwBlockDefinitionDataError {
-    fn from(value: quick_xml::DeError) -> Self {
-        Self::De(value.to_string())
+impl From<XmlReadError> for SwBlockDefinitionDataError {
+    fn from(value: XmlReadError) -> Self {
+        match value {
+            XmlReadError::Xml(mes) => Self::Xml(mes),
+            XmlReadError::De(mes) => Self::De(mes),
+        }
+    }
+}
+
+impl From<XmlWriteError> for SwBlockDefinitionDataError {
+    fn from(value: XmlWriteError) -> Self {
+        Self::Se(value.to_string())
     }
 }
 
@@ -114,6 +365,7 @@ impl fmt::Display for SwBlockDefinitionDataError {
         match self {
             Self::Io(mes) => write!(f, "IoError: {}", mes),
             Self::De(mes) => write!(f, "DeError: {}", mes),
+            Self::Se(mes) => write!(f, "SeError: {}", mes),
             Self::Xml(mes) => write!(f, "XmlError: {}", mes),
         }
     }
@@ -137,6 +389,16 @@ pub enum SwBlockDefinitionMeshKey {
 }
 
 impl SwBlockDefinitionMeshKey {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::MeshData => "mesh_data",
+            Self::Mesh0 => "mesh_0",
+            Self::Mesh1 => "mesh_1",
+            Self::Mesh2 => "mesh_2",
+            Self::MeshEditorOnly => "mesh_editor_only",
+        }
+    }
+
     pub fn xml_name(&self) -> &str {
         match self {
             Self::MeshData => "mesh_data_name",
@@ -183,4 +445,42 @@ impl SwBlockDefinitionMeshes {
     ) -> Option<&Result<SwMesh, SwMeshFromFileError>> {
         self.meshes.get(key)
     }
+
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<
+        Item = (
+            &SwBlockDefinitionMeshKey,
+            &Result<SwMesh, SwMeshFromFileError>,
+        ),
+    > {
+        self.meshes.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn discards_definitions_changed_while_loading() {
+        let dir = std::env::temp_dir().join(format!("swbd-definition-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.xml");
+        std::fs::write(&path, r#"<definition name="A"/>"#).unwrap();
+
+        let mut definition = SwBlockDefinition::new(&dir, &path).unwrap();
+        let (loaded, modified) = SwBlockDefinition::load(&path);
+        // 読み込んだ後に書き換えられた
+        let stale = modified.map(|time| time - Duration::from_secs(1));
+        assert!(!definition.set_loaded(loaded.clone(), stale));
+        assert!(!definition.is_loaded());
+
+        assert!(definition.set_loaded(loaded, modified));
+        assert_eq!(definition.data().unwrap().name.as_deref(), Some("A"));
+        assert!(!definition.is_modified_on_disk());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
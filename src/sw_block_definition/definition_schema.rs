@@ -1,8 +1,11 @@
+pub use super::xml_element::ExtraXml;
+use super::xml_element::{from_element, XmlElement, XmlReadError, XmlWriteError};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 macro_rules! define_vec3 {
     ($name:ident, $type:ty) => {
-        #[derive(Serialize, Deserialize, Default, Debug)]
+        #[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
         #[serde(default)]
         pub struct $name {
             #[serde(rename = "@x")]
@@ -11,12 +14,44 @@ macro_rules! define_vec3 {
             pub y: $type,
             #[serde(rename = "@z")]
             pub z: $type,
+
+            #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+            pub extra: ExtraXml,
         }
     };
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
-#[serde(rename = "definition", default, deny_unknown_fields)]
+macro_rules! define_attributes {
+    ($target:ident { $($name:literal => $field:ident: $kind:ident),* $(,)? }) => {
+        impl $target {
+            // XML 属性名と値の組をファイル中の順序で返す
+            pub fn attributes(&self) -> Vec<(&'static str, AttributeValue<'_>)> {
+                vec![$(($name, AttributeValue::$kind(&self.$field))),*]
+            }
+
+            pub fn attribute(&self, name: &str) -> Option<AttributeValue<'_>> {
+                match name {
+                    $($name => Some(AttributeValue::$kind(&self.$field)),)*
+                    _ => None,
+                }
+            }
+
+            // 属性名と値の型が一致したときだけ書き換えて true を返す
+            pub fn set_attribute(&mut self, name: &str, value: OwnedAttributeValue) -> bool {
+                match (name, value) {
+                    $(($name, OwnedAttributeValue::$kind(v)) => {
+                        self.$field = v;
+                        true
+                    })*
+                    _ => false,
+                }
+            }
+        }
+    };
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "definition", default)]
 pub struct Definition {
     #[serde(rename = "@name")]
     pub name: Option<String>,
@@ -306,22 +341,261 @@ pub struct Definition {
     pub weapon_cart_position: Vec<WeaponCartPosition>,
     pub weapon_cart_velocity: Vec<WeaponCartVelocity>,
     pub rope_hook_offset: Vec<RopeHookOffset>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
 impl Definition {
+    pub fn from_xml(xml: &str) -> Result<Self, XmlReadError> {
+        let root = XmlElement::parse_document(xml)?;
+        if root.name != "definition" {
+            return Err(XmlReadError::Xml(format!(
+                "Unexpected root element: {:?}",
+                root.name
+            )));
+        }
+        from_element(&root)
+    }
+
+    // ゲームのファイルと同じくタブでインデントし、要素と属性はフィールドの宣言順に並べる
+    pub fn to_xml(&self) -> Result<String, XmlWriteError> {
+        Ok(self.to_element()?.to_document())
+    }
+
+    // スキーマに無い属性と要素も含めた XML の木
+    pub fn to_element(&self) -> Result<XmlElement, XmlWriteError> {
+        XmlElement::from_serialize("definition", self)
+    }
+
     pub fn rope_hook_offset_last(&self) -> Option<&RopeHookOffset> {
         self.rope_hook_offset.last()
     }
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+define_attributes!(Definition {
+    "name" => name: String,
+    "category" => category: I32,
+    "type" => definition_type: I32,
+    "mass" => mass: F32,
+    "value" => value: F32,
+    "flags" => flags: U64,
+    "tags" => tags: String,
+    "phys_collision_dampen" => phys_collision_dampen: String,
+    "audio_filename_start" => audio_filename_start: String,
+    "audio_filename_loop" => audio_filename_loop: String,
+    "audio_filename_end" => audio_filename_end: String,
+    "audio_filename_start_b" => audio_filename_start_b: String,
+    "audio_filename_loop_b" => audio_filename_loop_b: String,
+    "audio_filename_end_b" => audio_filename_end_b: String,
+    "audio_gain" => audio_gain: F32,
+    "mesh_data_name" => mesh_data_name: String,
+    "mesh_0_name" => mesh_0_name: String,
+    "mesh_1_name" => mesh_1_name: String,
+    "mesh_2_name" => mesh_2_name: String,
+    "mesh_editor_only_name" => mesh_editor_only_name: String,
+    "block_type" => block_type: I32,
+    "child_name" => child_name: String,
+    "extender_name" => extender_name: String,
+    "constraint_type" => constraint_type: I32,
+    "constraint_axis" => constraint_axis: I32,
+    "constraint_range_of_motion" => constraint_range_of_motion: F32,
+    "max_motor_force" => max_motor_force: F32,
+    "max_motor_speed" => max_motor_speed: F32,
+    "cable_radius" => cable_radius: F32,
+    "cable_length" => cable_length: F32,
+    "seat_type" => seat_type: I32,
+    "seat_pose" => seat_pose: I32,
+    "seat_health_per_sec" => seat_health_per_sec: I32,
+    "buoy_radius" => buoy_radius: F32,
+    "buoy_factor" => buoy_factor: F32,
+    "buoy_force" => buoy_force: F32,
+    "force_emitter_max_force" => force_emitter_max_force: F32,
+    "force_emitter_max_vector" => force_emitter_max_vector: F32,
+    "force_emitter_default_pitch" => force_emitter_default_pitch: F32,
+    "force_emitter_blade_height" => force_emitter_blade_height: F32,
+    "force_emitter_rotation_speed" => force_emitter_rotation_speed: F32,
+    "force_emitter_blade_physics_length" => force_emitter_blade_physics_length: F32,
+    "force_emitter_blade_efficiency" => force_emitter_blade_efficiency: F32,
+    "force_emitter_efficiency" => force_emitter_efficiency: F32,
+    "engine_max_force" => engine_max_force: F32,
+    "engine_frictionless_force" => engine_frictionless_force: F32,
+    "trans_conn_type" => trans_conn_type: I32,
+    "trans_type" => trans_type: I32,
+    "wheel_radius" => wheel_radius: F32,
+    "wheel_width" => wheel_width: F32,
+    "wheel_wishbone_length" => wheel_wishbone_length: F32,
+    "wheel_suspension_height" => wheel_suspension_height: F32,
+    "wheel_wishbone_margin" => wheel_wishbone_margin: F32,
+    "wheel_suspension_offset" => wheel_suspension_offset: F32,
+    "wheel_wishbone_offset" => wheel_wishbone_offset: F32,
+    "wheel_type" => wheel_type: F32,
+    "button_type" => button_type: I32,
+    "light_intensity" => light_intensity: F32,
+    "light_range" => light_range: F32,
+    "light_ies_map" => light_ies_map: String,
+    "light_fov" => light_fov: F32,
+    "light_type" => light_type: I32,
+    "door_lower_limit" => door_lower_limit: F32,
+    "door_upper_limit" => door_upper_limit: F32,
+    "door_flipped" => door_flipped: Bool,
+    "custom_door_type" => custom_door_type: I32,
+    "door_side_dist" => door_side_dist: I32,
+    "door_up_dist" => door_up_dist: I32,
+    "dynamic_min_rotation" => dynamic_min_rotation: F32,
+    "dynamic_max_rotation" => dynamic_max_rotation: F32,
+    "logic_gate_type" => logic_gate_type: I32,
+    "logic_gate_subtype" => logic_gate_subtype: I32,
+    "indicator_type" => indicator_type: I32,
+    "connector_type" => connector_type: I32,
+    "magnet_force" => magnet_force: F32,
+    "gyro_type" => gyro_type: I32,
+    "reward_tier" => reward_tier: I32,
+    "revision" => revision: I32,
+    "rudder_surface_area" => rudder_surface_area: F32,
+    "pump_pressure" => pump_pressure: F32,
+    "m_pump_pressure" => m_pump_pressure: F32,
+    "water_component_type" => water_component_type: F32,
+    "torque_component_type" => torque_component_type: I32,
+    "jet_engine_component_type" => jet_engine_component_type: I32,
+    "particle_speed" => particle_speed: F32,
+    "inventory_type" => inventory_type: F32,
+    "inventory_default_outfit" => inventory_default_outfit: F32,
+    "inventory_class" => inventory_class: I32,
+    "inventory_default_item" => inventory_default_item: I32,
+    "electric_type" => electric_type: I32,
+    "electric_charge_capacity" => electric_charge_capacity: I32,
+    "electric_magnitude" => electric_magnitude: F32,
+    "composite_type" => composite_type: I32,
+    "camera_fov_min" => camera_fov_min: F32,
+    "camera_fov_max" => camera_fov_max: F32,
+    "monitor_border" => monitor_border: F32,
+    "monitor_inset" => monitor_inset: F32,
+    "weapon_type" => weapon_type: I32,
+    "weapon_class" => weapon_class: I32,
+    "weapon_belt_type" => weapon_belt_type: I32,
+    "weapon_ammo_capacity" => weapon_ammo_capacity: I32,
+    "weapon_ammo_feed" => weapon_ammo_feed: Bool,
+    "weapon_barrel_length_voxels" => weapon_barrel_length_voxels: I32,
+    "rx_range" => rx_range: F32,
+    "rx_length" => rx_length: F32,
+    "rocket_type" => rocket_type: I32,
+    "radar_range" => radar_range: F32,
+    "radar_speed" => radar_speed: F32,
+    "engine_module_type" => engine_module_type: I32,
+    "steam_component_type" => steam_component_type: I32,
+    "steam_component_capacity" => steam_component_capacity: F32,
+    "nuclear_component_type" => nuclear_component_type: I32,
+    "radar_type" => radar_type: I32,
+    "piston_len" => piston_len: F32,
+    "piston_cam" => piston_cam: F32,
+    "data_logger_component_type" => data_logger_component_type: I32,
+    "metadata_component_type" => metadata_component_type: I32,
+    "oil_component_type" => oil_component_type: I32,
+    "tool_type" => tool_type: I32,
+});
+
+#[derive(Debug, Clone, Copy)]
+pub enum AttributeValue<'a> {
+    Bool(&'a Option<bool>),
+    I32(&'a Option<i32>),
+    U64(&'a Option<u64>),
+    F32(&'a Option<f32>),
+    String(&'a Option<String>),
+}
+
+impl AttributeValue<'_> {
+    pub fn is_some(&self) -> bool {
+        match self {
+            Self::Bool(v) => v.is_some(),
+            Self::I32(v) => v.is_some(),
+            Self::U64(v) => v.is_some(),
+            Self::F32(v) => v.is_some(),
+            Self::String(v) => v.is_some(),
+        }
+    }
+
+    // 文字列をこの属性と同じ型の値として解釈する
+    pub fn parse(&self, text: &str) -> Option<OwnedAttributeValue> {
+        let trimmed = text.trim();
+        match self {
+            Self::Bool(_) => trimmed
+                .parse()
+                .ok()
+                .map(|v| OwnedAttributeValue::Bool(Some(v))),
+            Self::I32(_) => trimmed
+                .parse()
+                .ok()
+                .map(|v| OwnedAttributeValue::I32(Some(v))),
+            Self::U64(_) => trimmed
+                .parse()
+                .ok()
+                .map(|v| OwnedAttributeValue::U64(Some(v))),
+            Self::F32(_) => trimmed
+                .parse()
+                .ok()
+                .map(|v| OwnedAttributeValue::F32(Some(v))),
+            Self::String(_) => Some(OwnedAttributeValue::String(Some(text.to_string()))),
+        }
+    }
+
+    pub fn to_owned_value(self) -> OwnedAttributeValue {
+        match self {
+            Self::Bool(v) => OwnedAttributeValue::Bool(*v),
+            Self::I32(v) => OwnedAttributeValue::I32(*v),
+            Self::U64(v) => OwnedAttributeValue::U64(*v),
+            Self::F32(v) => OwnedAttributeValue::F32(*v),
+            Self::String(v) => OwnedAttributeValue::String(v.clone()),
+        }
+    }
+}
+
+// 編集や元に戻す操作のために属性値を保持する
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedAttributeValue {
+    Bool(Option<bool>),
+    I32(Option<i32>),
+    U64(Option<u64>),
+    F32(Option<f32>),
+    String(Option<String>),
+}
+
+impl OwnedAttributeValue {
+    pub fn as_value(&self) -> AttributeValue<'_> {
+        match self {
+            Self::Bool(v) => AttributeValue::Bool(v),
+            Self::I32(v) => AttributeValue::I32(v),
+            Self::U64(v) => AttributeValue::U64(v),
+            Self::F32(v) => AttributeValue::F32(v),
+            Self::String(v) => AttributeValue::String(v),
+        }
+    }
+}
+
+impl fmt::Display for AttributeValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(Some(v)) => write!(f, "{}", v),
+            Self::I32(Some(v)) => write!(f, "{}", v),
+            Self::U64(Some(v)) => write!(f, "{}", v),
+            Self::F32(Some(v)) => write!(f, "{}", v),
+            Self::String(Some(v)) => write!(f, "{}", v),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct SfxDatas {
     #[serde(default)]
     pub sfx_data: Vec<SfxData>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct SfxData {
     #[serde(rename = "@sfx_name")]
@@ -336,16 +610,22 @@ pub struct SfxData {
     pub sfx_is_underwater_affected: Option<bool>,
 
     pub sfx_layers: Vec<SfxLayers>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct SfxLayers {
     #[serde(default)]
     pub sfx_layer: Vec<SfxLayer>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct SfxLayer {
     #[serde(rename = "@sfx_filename_start")]
@@ -364,23 +644,32 @@ pub struct SfxLayer {
     pub sfx_volume_fade_speed: Option<f32>,
     #[serde(rename = "@sfx_pitch_fade_speed")]
     pub sfx_pitch_fade_speed: Option<f32>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct Surfaces {
     #[serde(default)]
     pub surface: Vec<Surface>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct BuoyancySurfaces {
     #[serde(default)]
     pub surface: Vec<Surface>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct Surface {
     #[serde(rename = "@orientation")]
@@ -399,16 +688,22 @@ pub struct Surface {
     pub is_two_sided: Option<bool>,
 
     pub position: Vec<Position>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct LogicNodes {
     #[serde(default)]
     pub logic_node: Vec<LogicNode>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct LogicNode {
     #[serde(rename = "@orientation")]
@@ -425,16 +720,22 @@ pub struct LogicNode {
     pub flags: Option<u64>,
 
     pub position: Vec<Position>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct Couplings {
     #[serde(default)]
     pub coupling: Vec<Coupling>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct Coupling {
     #[serde(rename = "@orientation")]
@@ -453,20 +754,26 @@ pub struct Coupling {
     pub allow_bipolar_alignment: Option<bool>,
 
     pub position: Vec<Position>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct Voxels {
     #[serde(default)]
     pub voxel: Vec<Voxel>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct Voxel {
     #[serde(rename = "@flags")]
-    pub flags: Option<i32>,
+    pub flags: Option<u64>,
     #[serde(rename = "@physics_shape")]
     pub physics_shape: Option<i32>,
     #[serde(rename = "@buoy_pipes")]
@@ -474,29 +781,36 @@ pub struct Voxel {
 
     pub position: Vec<Position>,
     pub physics_shape_rotation: Vec<PhysicsShapeRotation>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+// ゲームのファイルでは 0 の成分は省略されている
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct PhysicsShapeRotation {
     #[serde(rename = "@00", default = "one")]
     pub r00: i32,
-    #[serde(rename = "@01")]
+    #[serde(rename = "@01", skip_serializing_if = "is_zero")]
     pub r01: i32,
-    #[serde(rename = "@02")]
+    #[serde(rename = "@02", skip_serializing_if = "is_zero")]
     pub r02: i32,
-    #[serde(rename = "@10")]
+    #[serde(rename = "@10", skip_serializing_if = "is_zero")]
     pub r10: i32,
     #[serde(rename = "@11", default = "one")]
     pub r11: i32,
-    #[serde(rename = "@12")]
+    #[serde(rename = "@12", skip_serializing_if = "is_zero")]
     pub r12: i32,
-    #[serde(rename = "@20")]
+    #[serde(rename = "@20", skip_serializing_if = "is_zero")]
     pub r20: i32,
-    #[serde(rename = "@21")]
+    #[serde(rename = "@21", skip_serializing_if = "is_zero")]
     pub r21: i32,
     #[serde(rename = "@22", default = "one")]
     pub r22: i32,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
 impl Default for PhysicsShapeRotation {
@@ -511,6 +825,7 @@ impl Default for PhysicsShapeRotation {
             r20: 0,
             r21: 0,
             r22: 1,
+            extra: ExtraXml::default(),
         }
     }
 }
@@ -557,45 +872,287 @@ define_vec3!(WeaponCartPosition, f32);
 define_vec3!(WeaponCartVelocity, f32);
 define_vec3!(RopeHookOffset, f32);
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct TooltipProperties {
     #[serde(rename = "@description")]
     pub description: Option<String>,
     #[serde(rename = "@short_description")]
     pub short_description: Option<String>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct JetEngineConnectionsPrev {
     #[serde(default)]
     pub j: Vec<JetEngineConnection>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct JetEngineConnectionsNext {
     #[serde(default)]
     pub j: Vec<JetEngineConnection>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct JetEngineConnection {
     pub pos: Vec<Position>,
     pub normal: Vec<Normal>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct RewardProperties {
     #[serde(rename = "@tier")]
     pub tier: Option<i32>,
     #[serde(rename = "@number_rewarded")]
     pub number_rewarded: Option<i32>,
+
+    #[serde(rename = "$extra", skip_serializing_if = "ExtraXml::is_empty")]
+    pub extra: ExtraXml,
 }
 
 fn one() -> i32 {
     1
 }
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<definition mass="1.5" name="Tab&#9;&amp; &quot;quote&quot;" category="0" value="0.1">
+	<surfaces>
+		<surface orientation="1" shape="3" is_two_sided="true">
+			<position x="0" y="1" z="-1"/>
+		</surface>
+	</surfaces>
+	<logic_nodes/>
+	<voxels>
+		<voxel physics_shape="1">
+			<position x="0" y="0" z="0"/>
+			<physics_shape_rotation 00="1" 11="1" 22="1"/>
+		</voxel>
+	</voxels>
+	<bb_physics_min x="-0.25" y="0" z="1e-7"/>
+</definition>
+"#;
+
+    #[test]
+    fn parse_write_parse_round_trip() {
+        let data = Definition::from_xml(DEFINITION).unwrap();
+        let xml = data.to_xml().unwrap();
+        assert_eq!(Definition::from_xml(&xml).unwrap(), data);
+    }
+
+    #[test]
+    fn writes_in_schema_order_with_game_formatting() {
+        let data = Definition::from_xml(DEFINITION).unwrap();
+        assert_eq!(data.name.as_deref(), Some("Tab\t& \"quote\""));
+        assert_eq!(
+            data.to_xml().unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<definition name="Tab&#9;&amp; &quot;quote&quot;" category="0" mass="1.5" value="0.1">
+	<surfaces>
+		<surface orientation="1" shape="3" is_two_sided="true">
+			<position x="0" y="1" z="-1"/>
+		</surface>
+	</surfaces>
+	<logic_nodes/>
+	<voxels>
+		<voxel physics_shape="1">
+			<position x="0" y="0" z="0"/>
+			<physics_shape_rotation 00="1" 11="1" 22="1"/>
+		</voxel>
+	</voxels>
+	<bb_physics_min x="-0.25" y="0" z="0.0000001"/>
+</definition>
+"#
+        );
+    }
+
+    #[test]
+    fn keeps_unrecognized_content_of_nested_elements() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<definition name="A" future_root="1">
+	<sfx_datas>
+		<sfx_data sfx_name="s">
+			<sfx_layers>
+				<description>Some text</description>
+			</sfx_layers>
+		</sfx_data>
+	</sfx_datas>
+	<surfaces>
+		<surface orientation="0" shape="1" future_attr="7">
+			<position x="0" y="0" z="0" w="2"/>
+			<future_child a="b">
+				<grandchild/>
+			</future_child>
+		</surface>
+	</surfaces>
+	<future_element>note</future_element>
+</definition>
+"#;
+        let data = Definition::from_xml(xml).unwrap();
+        let surface = &data.surfaces[0].surface[0];
+        assert_eq!(
+            surface.extra.attributes,
+            [("future_attr".to_string(), "7".to_string())]
+        );
+        assert_eq!(surface.extra.elements[0].name, "future_child");
+        assert_eq!(surface.position[0].extra.attributes[0].0, "w");
+        assert_eq!(
+            data.sfx_datas[0].sfx_data[0].sfx_layers[0].extra.elements[0].text,
+            "Some text"
+        );
+        assert_eq!(data.extra.elements[0].text, "note");
+
+        // 全て元の位置のまま書き戻される
+        assert_eq!(data.to_xml().unwrap(), xml);
+    }
+
+    #[test]
+    fn keeps_empty_attributes() {
+        let xml = r#"<definition name="A" mass="">
+	<logic_nodes>
+		<logic_node label="" type="0"/>
+	</logic_nodes>
+</definition>"#;
+        let data = Definition::from_xml(xml).unwrap();
+        assert_eq!(data.mass, None);
+        let written = data.to_xml().unwrap();
+        assert!(
+            written.contains(r#"<definition name="A" mass="">"#),
+            "{}",
+            written
+        );
+        assert!(
+            written.contains(r#"<logic_node type="0" label=""/>"#),
+            "{}",
+            written
+        );
+
+        // 空だった属性に値を入れたらそちらを書き出す
+        let mut data = data;
+        data.set_attribute("mass", OwnedAttributeValue::F32(Some(2.0)));
+        assert!(data
+            .to_xml()
+            .unwrap()
+            .contains(r#"<definition name="A" mass="2">"#));
+    }
+
+    #[test]
+    fn rejects_other_root_element() {
+        assert!(matches!(
+            Definition::from_xml("<vehicle/>"),
+            Err(XmlReadError::Xml(_))
+        ));
+        assert!(matches!(
+            Definition::from_xml(r#"<definition mass="heavy"/>"#),
+            Err(XmlReadError::De(_))
+        ));
+    }
+
+    #[test]
+    fn removed_attribute_is_not_written() {
+        let mut data = Definition::from_xml(DEFINITION).unwrap();
+        assert!(data.set_attribute("value", OwnedAttributeValue::F32(None)));
+        assert!(!data.to_xml().unwrap().contains("value="));
+    }
+
+    // 属性の値を文字列のまま比べ、最初に違った場所を返す
+    // 属性の順序と、名前の違う子要素の順序は問わない
+    fn first_difference(a: &XmlElement, b: &XmlElement) -> Option<String> {
+        let mut a_attributes = a.attributes.clone();
+        let mut b_attributes = b.attributes.clone();
+        a_attributes.sort();
+        b_attributes.sort();
+        if a.name != b.name || a.text != b.text || a_attributes != b_attributes {
+            return Some(format!(
+                "<{}> {:?} / {:?}",
+                a.name, a_attributes, b_attributes
+            ));
+        }
+        let mut a_children: Vec<_> = a.children.iter().collect();
+        let mut b_children: Vec<_> = b.children.iter().collect();
+        a_children.sort_by(|x, y| x.name.cmp(&y.name));
+        b_children.sort_by(|x, y| x.name.cmp(&y.name));
+        if a_children.len() != b_children.len() {
+            return Some(format!("<{}> has a different number of children", a.name));
+        }
+        a_children
+            .iter()
+            .zip(&b_children)
+            .find_map(|(x, y)| first_difference(x, y))
+            .map(|difference| format!("<{}>/{}", a.name, difference))
+    }
+
+    // cargo test -- --ignored で実行する
+    // data/definitions の全ての定義を読んで書き出し、読み直して同じ内容になるか、
+    // 書き出した属性値の表記 (数値の書式を含む) が元のファイルと同じかを確かめる
+    #[test]
+    #[ignore]
+    fn round_trip_rom_definitions() {
+        let rom_path = std::env::var("SW_ROM_PATH").expect("SW_ROM_PATH is not set");
+        let definitions_path = std::path::Path::new(&rom_path)
+            .join("data")
+            .join("definitions");
+        let mut count = 0;
+        let mut failures = Vec::new();
+        for entry in std::fs::read_dir(&definitions_path).unwrap() {
+            let path = entry.unwrap().path();
+            if !path.extension().is_some_and(|ext| ext == "xml") {
+                continue;
+            }
+            count += 1;
+            let filename = path.file_name().unwrap().to_string_lossy().into_owned();
+            let xml = std::fs::read_to_string(&path).unwrap();
+            let result = Definition::from_xml(&xml)
+                .map_err(|err| err.to_string())
+                .and_then(|data| {
+                    let written = data.to_xml().map_err(|err| err.to_string())?;
+                    let reparsed = Definition::from_xml(&written).map_err(|err| err.to_string())?;
+                    if reparsed != data {
+                        return Err("reparsed contents differ".to_string());
+                    }
+                    let original =
+                        XmlElement::parse_document(&xml).map_err(|err| err.to_string())?;
+                    let written =
+                        XmlElement::parse_document(&written).map_err(|err| err.to_string())?;
+                    match first_difference(&original, &written) {
+                        Some(difference) => Err(format!("written XML differs at {}", difference)),
+                        None => Ok(()),
+                    }
+                });
+            if let Err(err) = result {
+                failures.push(format!("{}: {}", filename, err));
+            }
+        }
+        assert!(count > 0, "no definitions found in {}", rom_path);
+        assert!(
+            failures.is_empty(),
+            "{} of {} definitions failed:\n{}",
+            failures.len(),
+            count,
+            failures.join("\n")
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use super::definition_schema::{Definition, LogicNode, Surface, Voxel};
+
+// flags 属性のビット
+// どのビットも意味を記した資料が無く、ゲームのデータとも照合できていないので、
+// 名前は付けずにビット番号で扱う ("bit 13")
+
+// 立っているビットの番号を下位から順に返す
+pub fn flag_bits(flags: u64) -> Vec<u32> {
+    (0..64).filter(|bit| flags & (1 << bit) != 0).collect()
+}
+
+// 立っているビットを "bit 0, bit 13" のように表示する
+pub fn flag_labels(flags: u64) -> String {
+    flag_bits(flags)
+        .iter()
+        .map(|bit| format!("bit {}", bit))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+macro_rules! has_flag {
+    ($($target:ident),*) => {
+        $(
+            impl $target {
+                // flags の bit 番目のビットが立っているか
+                pub fn has_flag(&self, bit: u32) -> bool {
+                    bit < 64 && self.flags.unwrap_or(0) & (1 << bit) != 0
+                }
+            }
+        )*
+    };
+}
+
+has_flag!(Definition, Surface, Voxel, LogicNode);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_set_bits_by_number() {
+        assert_eq!(flag_bits(0x2001), vec![0, 13]);
+        assert_eq!(flag_labels(0x2001), "bit 0, bit 13");
+        assert_eq!(flag_labels(1 << 63), "bit 63");
+
+        let voxel = Voxel {
+            flags: Some(0b100),
+            ..Default::default()
+        };
+        assert!(voxel.has_flag(2));
+        assert!(!voxel.has_flag(0));
+        assert!(!voxel.has_flag(64));
+    }
+}
@@ -0,0 +1,258 @@
+use super::{
+    definition::LoadedDefinition, definition_schema::Definition, SwBlockDefinition,
+    SwBlockDefinitionMeshes,
+};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc, Condvar, Mutex,
+    },
+    time::SystemTime,
+};
+
+// 定義ファイルとメッシュをワーカースレッドで並列に読み込む
+// メッシュは大きいので、必要な定義 (選択中のもの) の分だけ request_meshes で読み込む
+// wasm ではスレッドを使わず、poll のたびに 1 件ずつ読み込む
+pub struct DefinitionLoader {
+    shared: Arc<Shared>,
+    #[cfg(target_arch = "wasm32")]
+    sender: mpsc::Sender<LoadResult>,
+    receiver: mpsc::Receiver<LoadResult>,
+    // 読み込み待ちと読み込み中の定義の添字
+    pending: BTreeSet<usize>,
+    // メッシュの読み込み待ちと読み込み中の定義の添字
+    pending_meshes: BTreeSet<usize>,
+    // ROM フォルダを開き直したら増やし、古い結果を捨てる
+    generation: u64,
+}
+
+pub struct LoadResult {
+    generation: u64,
+    pub index: usize,
+    pub path: PathBuf,
+    pub loaded: Loaded,
+}
+
+pub enum Loaded {
+    // 読み込んだ定義と、読み込む前のファイルの更新日時
+    Definition(LoadedDefinition, Option<SystemTime>),
+    // 読み込みに使った定義と、そのメッシュ
+    Meshes(Arc<Definition>, Arc<SwBlockDefinitionMeshes>),
+}
+
+struct Job {
+    generation: u64,
+    index: usize,
+    rom_path: PathBuf,
+    path: PathBuf,
+    // Some ならこの定義のメッシュを読み込む
+    meshes_of: Option<Arc<Definition>>,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<VecDeque<Job>>,
+    condvar: Condvar,
+    shutdown: AtomicBool,
+    generation: AtomicU64,
+}
+
+impl DefinitionLoader {
+    pub fn new() -> Self {
+        let shared = Arc::new(Shared::default());
+        let (sender, receiver) = mpsc::channel();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let workers = std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+                .clamp(1, 8);
+            for i in 0..workers {
+                let shared = shared.clone();
+                let sender = sender.clone();
+                let _ = std::thread::Builder::new()
+                    .name(format!("definition-loader-{}", i))
+                    .spawn(move || worker(&shared, &sender));
+            }
+        }
+
+        Self {
+            shared,
+            #[cfg(target_arch = "wasm32")]
+            sender,
+            receiver,
+            pending: BTreeSet::new(),
+            pending_meshes: BTreeSet::new(),
+            generation: 0,
+        }
+    }
+
+    // 読み込みを予約する。priority なら他より先に読み込む
+    pub fn request(&mut self, index: usize, definition: &SwBlockDefinition, priority: bool) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if self.pending.contains(&index) {
+            if priority {
+                // まだ始まっていなければ先頭に移す
+                if let Some(pos) = queue
+                    .iter()
+                    .position(|job| job.index == index && job.meshes_of.is_none())
+                {
+                    if pos > 0 {
+                        let job = queue.remove(pos).unwrap();
+                        queue.push_front(job);
+                    }
+                }
+            }
+            return;
+        }
+
+        let job = Job {
+            generation: self.generation,
+            index,
+            rom_path: definition.rom_path().to_path_buf(),
+            path: definition.path().to_path_buf(),
+            meshes_of: None,
+        };
+        if priority {
+            queue.push_front(job);
+        } else {
+            queue.push_back(job);
+        }
+        self.pending.insert(index);
+        self.shared.condvar.notify_one();
+    }
+
+    // data のメッシュの読み込みを他より先に予約する
+    pub fn request_meshes(
+        &mut self,
+        index: usize,
+        definition: &SwBlockDefinition,
+        data: Arc<Definition>,
+    ) {
+        if !self.pending_meshes.insert(index) {
+            return;
+        }
+        self.shared.queue.lock().unwrap().push_front(Job {
+            generation: self.generation,
+            index,
+            rom_path: definition.rom_path().to_path_buf(),
+            path: definition.path().to_path_buf(),
+            meshes_of: Some(data),
+        });
+        self.shared.condvar.notify_one();
+    }
+
+    // 読み込み終わった定義とメッシュを返す
+    pub fn poll(&mut self) -> Vec<LoadResult> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let job = self.shared.queue.lock().unwrap().pop_front();
+            if let Some(job) = job {
+                run_job(&self.shared, job, &self.sender);
+            }
+        }
+
+        let generation = self.generation;
+        let results: Vec<LoadResult> = self
+            .receiver
+            .try_iter()
+            .filter(|result| result.generation == generation)
+            .collect();
+        for result in &results {
+            match result.loaded {
+                Loaded::Definition(..) => self.pending.remove(&result.index),
+                Loaded::Meshes(..) => self.pending_meshes.remove(&result.index),
+            };
+        }
+        results
+    }
+
+    pub fn is_pending(&self, index: usize) -> bool {
+        self.pending.contains(&index)
+    }
+
+    pub fn is_meshes_pending(&self, index: usize) -> bool {
+        self.pending_meshes.contains(&index)
+    }
+
+    // 読み込み待ちと読み込み中の定義とメッシュの数
+    pub fn pending_count(&self) -> usize {
+        self.pending.len() + self.pending_meshes.len()
+    }
+
+    // 予約を全て取り消す。読み込み中のものの結果は捨てる
+    pub fn reset(&mut self) {
+        self.generation += 1;
+        self.shared
+            .generation
+            .store(self.generation, Ordering::SeqCst);
+        self.shared.queue.lock().unwrap().clear();
+        self.pending.clear();
+        self.pending_meshes.clear();
+    }
+}
+
+impl Default for DefinitionLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for DefinitionLoader {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        self.shared.condvar.notify_all();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn worker(shared: &Shared, sender: &mpsc::Sender<LoadResult>) {
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if shared.shutdown.load(Ordering::SeqCst) {
+                    return;
+                }
+                if let Some(job) = queue.pop_front() {
+                    break job;
+                }
+                queue = shared.condvar.wait(queue).unwrap();
+            }
+        };
+        if !run_job(shared, job, sender) {
+            return;
+        }
+    }
+}
+
+// 受け取る側が無くなったら false を返す
+fn run_job(shared: &Shared, job: Job, sender: &mpsc::Sender<LoadResult>) -> bool {
+    if job.generation != shared.generation.load(Ordering::SeqCst) {
+        return true;
+    }
+    let loaded = match job.meshes_of {
+        Some(data) => {
+            let meshes = SwBlockDefinition::load_meshes(&data, &job.rom_path);
+            Loaded::Meshes(data, meshes)
+        }
+        None => {
+            let (loaded, modified) = SwBlockDefinition::load(&job.path);
+            Loaded::Definition(loaded, modified)
+        }
+    };
+    if job.generation != shared.generation.load(Ordering::SeqCst) {
+        return true;
+    }
+    sender
+        .send(LoadResult {
+            generation: job.generation,
+            index: job.index,
+            path: job.path,
+            loaded,
+        })
+        .is_ok()
+}
@@ -0,0 +1,292 @@
+use super::definition_schema;
+use crate::gl_renderer::{Color4, Line, LineVertex, Mesh, MeshVertex, SceneObject};
+use glam::{Mat4, Quat, Vec3};
+use std::f32::consts::PI;
+
+const MARKER_SIZE: f32 = 0.03;
+const ARROW_LENGTH: f32 = 0.15;
+const COUPLING_SIZE: f32 = 0.06;
+
+const COUPLING_COLOR: Color4 = Color4 {
+    r: 0.2,
+    g: 0.8,
+    b: 0.8,
+    a: 1.0,
+};
+const COUPLING_HIGHLIGHT_COLOR: Color4 = Color4 {
+    r: 1.0,
+    g: 1.0,
+    b: 0.3,
+    a: 1.0,
+};
+const ALIGNMENT_REQUIRED_COLOR: Color4 = Color4 {
+    r: 1.0,
+    g: 0.5,
+    b: 0.0,
+    a: 1.0,
+};
+const ALIGNMENT_FREE_COLOR: Color4 = Color4 {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+// ロジックノードを置かれた面に小さな立方体と向きを表す矢印で表示する
+// 立方体は type ごと、矢印は mode (入力/出力) ごとに色分けする
+pub fn create_logic_node_objects(node: &definition_schema::LogicNode) -> Vec<SceneObject> {
+    create_marker_objects(
+        logic_node_position(node),
+        orientation_direction(node.orientation.unwrap_or(0)),
+        logic_node_type_color(node.node_type.unwrap_or(0)),
+        logic_node_mode_color(node.mode.unwrap_or(0)),
+    )
+}
+
+// マーカーを置く面の中心 (ビューアの右手系)
+pub fn logic_node_position(node: &definition_schema::LogicNode) -> Vec3 {
+    face_center(
+        node.position.last(),
+        orientation_direction(node.orientation.unwrap_or(0)),
+    )
+}
+
+fn logic_node_type_color(node_type: i32) -> Color4 {
+    match node_type {
+        0 => Color4::new(0.3, 0.9, 0.3, 1.0),
+        1 => Color4::new(1.0, 0.6, 0.1, 1.0),
+        2 => Color4::new(0.6, 0.6, 0.6, 1.0),
+        3 => Color4::new(0.0, 0.6, 0.9, 1.0),
+        4 => Color4::new(1.0, 0.9, 0.1, 1.0),
+        5 => Color4::new(0.7, 0.3, 0.9, 1.0),
+        6 => Color4::new(0.2, 0.4, 1.0, 1.0),
+        7 => Color4::new(1.0, 0.4, 0.7, 1.0),
+        8 => Color4::new(0.6, 0.45, 0.3, 1.0),
+        _ => Color4::WHITE,
+    }
+}
+
+fn logic_node_mode_color(mode: i32) -> Color4 {
+    match mode {
+        0 => Color4::new(1.0, 0.3, 0.2, 1.0),
+        1 => Color4::new(0.2, 0.9, 1.0, 1.0),
+        _ => Color4::WHITE,
+    }
+}
+
+// カプラーを面の外向きに表示する
+// オスは突き出た四角錐、メスは内側にすぼまった穴、それ以外は立方体
+// 面に沿った線は alignment の向きで、alignment_required ならオレンジ、
+// allow_bipolar_alignment なら反対側にも伸ばす
+pub fn create_coupling_objects(
+    coupling: &definition_schema::Coupling,
+    highlighted: bool,
+) -> Vec<SceneObject> {
+    let direction_lh = orientation_direction(coupling.orientation.unwrap_or(0));
+    let direction = to_rh(direction_lh);
+    let transform_matrix = Some(Mat4::from_translation(coupling_position(coupling)));
+    let (color, line_width) = if highlighted {
+        (COUPLING_HIGHLIGHT_COLOR, 3.0)
+    } else {
+        (COUPLING_COLOR, 1.5)
+    };
+
+    // u × v = direction となる面内の軸
+    let u = direction.any_orthogonal_vector().normalize();
+    let v = direction.cross(u);
+    let corners = [-u - v, u - v, u + v, -u + v].map(|c| COUPLING_SIZE * c);
+
+    let mut objects = Vec::new();
+    match coupling.coupling_gender {
+        Some(0) => {
+            let apex = 1.5 * COUPLING_SIZE * direction;
+            let mut triangles: Vec<[Vec3; 3]> = (0..4)
+                .map(|i| [corners[i], corners[(i + 1) % 4], apex])
+                .collect();
+            triangles.push([corners[0], corners[3], corners[2]]);
+            triangles.push([corners[0], corners[2], corners[1]]);
+            objects.push(SceneObject::from_mesh(
+                triangle_mesh(&triangles, color),
+                transform_matrix,
+            ));
+        }
+        Some(1) => {
+            let bottom = corners.map(|c| 0.5 * c - COUPLING_SIZE * direction);
+            let mut lines = Vec::new();
+            for i in 0..4 {
+                lines.push((corners[i], corners[(i + 1) % 4]));
+                lines.push((bottom[i], bottom[(i + 1) % 4]));
+                lines.push((corners[i], bottom[i]));
+            }
+            objects.push(SceneObject::from_line(
+                line_segments(&lines, color, line_width),
+                transform_matrix,
+            ));
+        }
+        _ => {
+            objects.push(SceneObject::from_mesh(
+                cube_mesh(MARKER_SIZE, color),
+                transform_matrix,
+            ));
+        }
+    }
+
+    let alignment_color = if coupling.alignment_required.unwrap_or(false) {
+        ALIGNMENT_REQUIRED_COLOR
+    } else {
+        ALIGNMENT_FREE_COLOR
+    };
+    let alignment = to_rh(alignment_direction(
+        direction_lh,
+        coupling.alignment.unwrap_or(0),
+    ));
+    let offset = 0.01 * direction;
+    let end = offset + 2.0 * COUPLING_SIZE * alignment;
+    let mut lines = vec![(offset, end)];
+    if coupling.allow_bipolar_alignment.unwrap_or(false) {
+        lines.push((offset, offset - 2.0 * COUPLING_SIZE * alignment));
+    }
+    objects.push(SceneObject::from_line(
+        line_segments(&lines, alignment_color, line_width),
+        transform_matrix,
+    ));
+
+    objects
+}
+
+// カプラーを置く面の中心 (ビューアの右手系)
+pub fn coupling_position(coupling: &definition_schema::Coupling) -> Vec3 {
+    face_center(
+        coupling.position.last(),
+        orientation_direction(coupling.orientation.unwrap_or(0)),
+    )
+}
+
+pub fn coupling_gender_name(gender: i32) -> &'static str {
+    match gender {
+        0 => "Male",
+        1 => "Female",
+        _ => "Neutral",
+    }
+}
+
+// 面に沿った alignment の基準方向を direction 周りに 90 度ずつ回す (ゲームの左手系)
+// 基準は上下の面なら +Z、それ以外は +Y
+fn alignment_direction(direction: Vec3, alignment: i32) -> Vec3 {
+    let base = if direction.y.abs() > 0.5 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    Quat::from_axis_angle(direction, PI / 2.0 * alignment as f32).mul_vec3(base)
+}
+
+// orientation の向き (ゲームの左手系)
+// 0: +X, 1: -X, 2: +Y, 3: -Y, 4: +Z, 5: -Z
+pub fn orientation_direction(orientation: i32) -> Vec3 {
+    match orientation {
+        1 => Vec3::NEG_X,
+        2 => Vec3::Y,
+        3 => Vec3::NEG_Y,
+        4 => Vec3::Z,
+        5 => Vec3::NEG_Z,
+        _ => Vec3::X,
+    }
+}
+
+// ボクセルの中心から direction 側の面の中心 (ビューアの右手系)
+fn face_center(position: Option<&definition_schema::Position>, direction: Vec3) -> Vec3 {
+    let voxel = match position {
+        Some(position) => 0.25 * Vec3::new(position.x as f32, position.y as f32, position.z as f32),
+        None => Vec3::ZERO,
+    };
+    to_rh(voxel + 0.125 * direction)
+}
+
+pub(super) fn to_rh(v: Vec3) -> Vec3 {
+    Vec3::new(v.x, v.y, -v.z)
+}
+
+fn create_marker_objects(
+    position: Vec3,
+    direction_lh: Vec3,
+    body_color: Color4,
+    arrow_color: Color4,
+) -> Vec<SceneObject> {
+    let direction = to_rh(direction_lh);
+    let transform_matrix = Some(Mat4::from_translation(position));
+
+    // 矢じりは矢印と直交する軸の方向に開く
+    let side = direction.any_orthogonal_vector();
+    let tip = ARROW_LENGTH * direction;
+    let head = tip - 0.3 * ARROW_LENGTH * direction;
+    let arrow = [
+        (Vec3::ZERO, tip),
+        (tip, head + 0.3 * ARROW_LENGTH * side),
+        (tip, head - 0.3 * ARROW_LENGTH * side),
+    ];
+
+    vec![
+        SceneObject::from_mesh(cube_mesh(MARKER_SIZE, body_color), transform_matrix),
+        SceneObject::from_line(line_segments(&arrow, arrow_color, 2.0), transform_matrix),
+    ]
+}
+
+pub(super) fn line_segments(segments: &[(Vec3, Vec3)], color: Color4, line_width: f32) -> Line {
+    let vertices = segments
+        .iter()
+        .flat_map(|&(a, b)| {
+            [
+                LineVertex { position: a, color },
+                LineVertex { position: b, color },
+            ]
+        })
+        .collect();
+    Line::new(vertices, line_width)
+}
+
+// 面ごとに法線を持つ三角形の集まり (ビューアの右手系、反時計回りが表)
+pub(super) fn triangle_mesh(triangles: &[[Vec3; 3]], color: Color4) -> Mesh {
+    let mut vertices = Vec::new();
+    for [p0, p1, p2] in triangles {
+        let normal = (*p1 - *p0).cross(*p2 - *p0).normalize();
+        for position in [*p0, *p1, *p2] {
+            vertices.push(MeshVertex {
+                position,
+                color,
+                normal,
+            });
+        }
+    }
+    let triangles = (0..triangles.len())
+        .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+        .collect();
+    Mesh::new(vertices, triangles)
+}
+
+// 原点を中心とする立方体 (ビューアの右手系)
+fn cube_mesh(half_size: f32, color: Color4) -> Mesh {
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+    // 法線 n と u × v = n となる面内の軸
+    for (n, u, v) in [
+        (Vec3::X, Vec3::Y, Vec3::Z),
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+        (Vec3::Y, Vec3::Z, Vec3::X),
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+        (Vec3::Z, Vec3::X, Vec3::Y),
+        (Vec3::NEG_Z, Vec3::Y, Vec3::X),
+    ] {
+        let i0 = vertices.len();
+        for corner in [n - u - v, n + u - v, n + u + v, n - u + v] {
+            vertices.push(MeshVertex {
+                position: half_size * corner,
+                color,
+                normal: n,
+            });
+        }
+        triangles.push([i0, i0 + 1, i0 + 2]);
+        triangles.push([i0, i0 + 2, i0 + 3]);
+    }
+    Mesh::new(vertices, triangles)
+}
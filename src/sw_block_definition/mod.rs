@@ -1,6 +1,39 @@
+mod attribute_enum;
+pub use attribute_enum::{
+    attribute_value_label, attribute_value_names, BlockType, Category, ConstraintType,
+    DefinitionType, ElectricType, LightType, LogicGateType, LogicNodeMode, LogicNodeType, SeatType,
+    WeaponType, NAMED_CODED_ATTRIBUTES, UNNAMED_CODED_ATTRIBUTES,
+};
 mod definition;
-pub use definition::{SwBlockDefinition, SwBlockDefinitionMeshKey};
+pub use definition::{
+    DiskChange, LoadedDefinition, SwBlockDefinition, SwBlockDefinitionDataError,
+    SwBlockDefinitionMeshKey, SwBlockDefinitionMeshes,
+};
 pub mod definition_schema;
+mod flags;
+pub use flags::{flag_bits, flag_labels};
+mod loader;
+pub use loader::{DefinitionLoader, LoadResult, Loaded};
+mod marker;
+pub use marker::{
+    coupling_gender_name, coupling_position, create_coupling_objects, create_logic_node_objects,
+    logic_node_position, orientation_direction,
+};
+mod search;
+pub use search::{CompareOp, SearchIndex, SearchQuery, SearchTerm};
 mod surface_mesh;
 mod sw_mesh;
-pub use surface_mesh::create_surface_object;
+pub use surface_mesh::{
+    create_surface_mesh, create_surface_object, is_known_surface_shape, surface_shape_warnings,
+};
+pub use sw_mesh::{
+    SwMesh, SwMeshFromFileError, SwMeshTriangle, SwMeshType, SwMeshVertex, SwSubmesh,
+};
+mod voxel_mesh;
+pub use voxel_mesh::{create_voxel_bounds_object, create_voxel_objects};
+#[cfg(not(target_arch = "wasm32"))]
+mod watcher;
+#[cfg(not(target_arch = "wasm32"))]
+pub use watcher::DefinitionWatcher;
+mod xml_element;
+pub use xml_element::{ExtraXml, XmlElement, XmlReadError, XmlWriteError};
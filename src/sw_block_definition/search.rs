@@ -0,0 +1,351 @@
+use super::{definition_schema::Definition, SwBlockDefinition, XmlElement};
+
+// 検索語は空白区切りで、すべての語に一致した定義を返す
+//   wheel               ファイル名、name 属性、tags のいずれかに含まれる
+//   tag:basic           tags にカンマ区切りで含まれる
+//   has:logic_nodes     要素か属性が存在する
+//   flag:13             flags の 13 番目のビットが立っている
+//                       ビットの名前は分からないので番号で指定する。64 以上や数値でなければエラー
+//   category=5 mass>10  属性の値を比べる (子要素の属性も対象)
+// 値に空白を含めるときは "..." で囲む
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchQuery {
+    terms: Vec<SearchTerm>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchTerm {
+    Text(String),
+    Tag(String),
+    Has(String),
+    // 調べるビットのマスク
+    Flag(u64),
+    Compare {
+        attribute: String,
+        op: CompareOp,
+        value: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl SearchQuery {
+    // 解釈できない語があればその理由を返す
+    pub fn parse(text: &str) -> Result<Self, String> {
+        Ok(Self {
+            terms: split_words(text)
+                .iter()
+                .map(|word| SearchTerm::parse(word))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn terms(&self) -> &Vec<SearchTerm> {
+        &self.terms
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+}
+
+impl SearchTerm {
+    fn parse(word: &str) -> Result<Self, String> {
+        if let Some(tag) = word.strip_prefix("tag:") {
+            return Ok(Self::Tag(tag.to_lowercase()));
+        }
+        if let Some(name) = word.strip_prefix("has:") {
+            return Ok(Self::Has(name.to_lowercase()));
+        }
+        if let Some(name) = word.strip_prefix("flag:") {
+            return match name.parse::<u32>() {
+                Ok(bit) if bit < 64 => Ok(Self::Flag(1 << bit)),
+                Ok(bit) => Err(format!("Flag bit out of range: {}", bit)),
+                Err(_) => Err(format!("Flag must be a bit number: {}", name)),
+            };
+        }
+
+        if let Some(pos) = word.find(['=', '!', '<', '>']) {
+            let (attribute, rest) = word.split_at(pos);
+            let (op, value) = match rest.as_bytes() {
+                [b'!', b'=', ..] => (Some(CompareOp::Ne), &rest[2..]),
+                [b'<', b'=', ..] => (Some(CompareOp::Le), &rest[2..]),
+                [b'>', b'=', ..] => (Some(CompareOp::Ge), &rest[2..]),
+                [b'=', ..] => (Some(CompareOp::Eq), &rest[1..]),
+                [b'<', ..] => (Some(CompareOp::Lt), &rest[1..]),
+                [b'>', ..] => (Some(CompareOp::Gt), &rest[1..]),
+                _ => (None, rest),
+            };
+            if let (false, Some(op)) = (attribute.is_empty(), op) {
+                return Ok(Self::Compare {
+                    attribute: attribute.to_lowercase(),
+                    op,
+                    value: value.to_lowercase(),
+                });
+            }
+        }
+
+        Ok(Self::Text(word.to_lowercase()))
+    }
+}
+
+impl CompareOp {
+    fn matches(&self, actual: &str, expected: &str) -> bool {
+        let numbers = actual.parse::<f64>().ok().zip(expected.parse::<f64>().ok());
+        match (self, numbers) {
+            (Self::Eq, Some((a, b))) => a == b,
+            (Self::Eq, None) => actual == expected,
+            (Self::Ne, _) => !Self::Eq.matches(actual, expected),
+            (Self::Lt, Some((a, b))) => a < b,
+            (Self::Le, Some((a, b))) => a <= b,
+            (Self::Gt, Some((a, b))) => a > b,
+            (Self::Ge, Some((a, b))) => a >= b,
+            // 数値でない値の大小は比べない
+            (_, None) => false,
+        }
+    }
+}
+
+// 空白で区切る。"..." の中の空白は区切りにしない
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+// 1つの定義から集めた検索用の文字列。すべて小文字にしてある
+#[derive(Debug, Default, Clone)]
+struct SearchEntry {
+    filename: String,
+    name: String,
+    tags: Vec<String>,
+    // 子要素も含むすべての属性
+    attributes: Vec<(String, String)>,
+    // 中身のある要素の名前
+    elements: Vec<String>,
+    flags: u64,
+}
+
+impl SearchEntry {
+    fn new(filename: &str, data: Option<&Definition>) -> Self {
+        let mut entry = Self {
+            filename: filename.to_lowercase(),
+            ..Default::default()
+        };
+        let Some(data) = data else {
+            return entry;
+        };
+
+        entry.name = data.name.clone().unwrap_or_default().to_lowercase();
+        entry.flags = data.flags.unwrap_or(0);
+        entry.tags = data
+            .tags
+            .iter()
+            .flat_map(|tags| tags.split(','))
+            .map(|tag| tag.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        if let Ok(root) = data.to_element() {
+            entry.add_attributes(&root);
+            for child in &root.children {
+                entry.add_element(child);
+            }
+        }
+        entry
+    }
+
+    // 読み込み済みの定義から作る。読み込みに失敗した定義はファイル名だけで作る
+    fn from_loaded(definition: &SwBlockDefinition) -> Option<Self> {
+        definition.is_loaded().then(|| {
+            let data = definition.loaded_data();
+            Self::new(&definition.filename(), data.as_deref())
+        })
+    }
+
+    fn add_element(&mut self, element: &XmlElement) {
+        if !element.attributes.is_empty() || !element.children.is_empty() {
+            self.elements.push(element.name.to_lowercase());
+        }
+        self.add_attributes(element);
+        for child in &element.children {
+            self.add_element(child);
+        }
+    }
+
+    fn add_attributes(&mut self, element: &XmlElement) {
+        self.attributes.extend(
+            element
+                .attributes
+                .iter()
+                .map(|(name, value)| (name.to_lowercase(), value.to_lowercase())),
+        );
+    }
+
+    fn matches(&self, term: &SearchTerm) -> bool {
+        match term {
+            SearchTerm::Text(text) => {
+                self.filename.contains(text)
+                    || self.name.contains(text)
+                    || self.tags.iter().any(|tag| tag.contains(text))
+            }
+            SearchTerm::Tag(tag) => self.tags.contains(tag),
+            SearchTerm::Has(name) => {
+                self.elements.contains(name) || self.attributes.iter().any(|(n, _)| n == name)
+            }
+            SearchTerm::Flag(mask) => self.flags & mask != 0,
+            SearchTerm::Compare {
+                attribute,
+                op,
+                value,
+            } => {
+                let mut values = self
+                    .attributes
+                    .iter()
+                    .filter(|(name, _)| name == attribute)
+                    .map(|(_, actual)| actual);
+                match op {
+                    // 同名の属性のどれも一致しないこと
+                    CompareOp::Ne => !values.any(|actual| CompareOp::Eq.matches(actual, value)),
+                    _ => values.any(|actual| op.matches(actual, value)),
+                }
+            }
+        }
+    }
+}
+
+// definitions と同じ順序で検索用の情報を持つ
+// ファイルは読まず、読み込み済みの定義だけを載せる
+// 定義を読み込んだり編集したりしたら、その定義の分だけ update で作り直す
+#[derive(Debug, Default, Clone)]
+pub struct SearchIndex {
+    entries: Vec<Option<SearchEntry>>,
+}
+
+impl SearchIndex {
+    pub fn build(definitions: &[SwBlockDefinition]) -> Self {
+        Self {
+            entries: definitions.iter().map(SearchEntry::from_loaded).collect(),
+        }
+    }
+
+    // index 番目の定義の検索用の情報だけ作り直す
+    pub fn update(&mut self, index: usize, definition: &SwBlockDefinition) {
+        if let Some(entry) = self.entries.get_mut(index) {
+            *entry = SearchEntry::from_loaded(definition);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // 索引に載っている (読み込み済みの) 定義の数
+    pub fn indexed_count(&self) -> usize {
+        self.entries.iter().flatten().count()
+    }
+
+    // 一致した定義の definitions 内の添字を返す
+    // まだ索引に載っていない定義は含めない
+    pub fn search(&self, query: &SearchQuery) -> Vec<usize> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| Some((i, entry.as_ref()?)))
+            .filter(|(_, entry)| query.terms.iter().all(|term| entry.matches(term)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sw_block_definition::definition_schema::OwnedAttributeValue;
+
+    #[test]
+    fn resolves_flag_bits_when_parsing() {
+        let query = SearchQuery::parse("flag:29 flag:5").unwrap();
+        assert_eq!(
+            query.terms(),
+            &vec![SearchTerm::Flag(1 << 29), SearchTerm::Flag(1 << 5)]
+        );
+    }
+
+    #[test]
+    fn rejects_flag_names_and_out_of_range_bits() {
+        assert_eq!(
+            SearchQuery::parse("wheel flag:deprecated"),
+            Err("Flag must be a bit number: deprecated".to_string())
+        );
+        assert!(SearchQuery::parse("flag:64").is_err());
+    }
+
+    #[test]
+    fn indexes_loaded_definitions_and_rebuilds_edited_entries() {
+        let rom_path = std::env::temp_dir().join(format!("swbd-search-{}", std::process::id()));
+        let definitions_path = rom_path.join("data").join("definitions");
+        std::fs::create_dir_all(&definitions_path).unwrap();
+        let mut definitions: Vec<SwBlockDefinition> = ["a.xml", "b.xml"]
+            .into_iter()
+            .map(|filename| {
+                let path = definitions_path.join(filename);
+                std::fs::write(&path, r#"<definition name="Wheel" mass="1"/>"#).unwrap();
+                SwBlockDefinition::new(&rom_path, path).unwrap()
+            })
+            .collect();
+
+        // 読み込む前は索引に載らない
+        let mut index = SearchIndex::build(&definitions);
+        assert_eq!(index.indexed_count(), 0);
+        assert!(index
+            .search(&SearchQuery::parse("wheel").unwrap())
+            .is_empty());
+
+        definitions[0].data().unwrap();
+        index.update(0, &definitions[0]);
+        assert_eq!(index.search(&SearchQuery::parse("wheel").unwrap()), vec![0]);
+
+        let mut index = SearchIndex::build(&definitions);
+        let query = SearchQuery::parse("mass>5").unwrap();
+        assert!(index.search(&query).is_empty());
+
+        definitions[1]
+            .set_attribute("mass", OwnedAttributeValue::F32(Some(10.0)))
+            .unwrap();
+        index.update(1, &definitions[1]);
+        assert_eq!(index.search(&query), vec![1]);
+        assert_eq!(
+            index.search(&SearchQuery::parse("wheel").unwrap()),
+            vec![0, 1]
+        );
+
+        std::fs::remove_dir_all(&rom_path).unwrap();
+    }
+}
@@ -33,8 +33,18 @@ const PIPE_COLOR_POWER: Color4 = Color4 {
 };
 */
 
+// 形がわからない面の目印
+const SURFACE_COLOR_UNKNOWN: Color4 = Color4 {
+    r: 1.0,
+    g: 0.0,
+    b: 1.0,
+    a: 1.0,
+};
+
+// color が半透明なら面も半透明で、trans_type が 0 以外ならガラスとして描画する
 pub fn create_surface_object(
     surface: &definition_schema::Surface,
+    color: Color4,
     show_surface: bool,
     show_edge: bool,
 ) -> (Option<SceneObject>, Option<SceneObject>) {
@@ -42,6 +52,49 @@ pub fn create_surface_object(
         return (None, None);
     }
 
+    let transform_matrix = Some(surface_transform(surface));
+    let (mesh, line) = surface_geometry(surface.shape.unwrap_or(0), color, show_surface, show_edge);
+
+    (
+        mesh.map(|mesh| {
+            let mut mesh = surface_sides(mesh, surface);
+            if color.a < 1.0 {
+                mesh.physics();
+            } else if surface.trans_type.unwrap_or(0) != 0 {
+                mesh.glass();
+            }
+            SceneObject::from_mesh(mesh, transform_matrix)
+        }),
+        line.map(|positions| {
+            SceneObject::from_line(
+                Line::single_color_lh(positions, Color4::BLACK, 1.0, true),
+                transform_matrix,
+            )
+        }),
+    )
+}
+
+// 座標変換を適用済みのメッシュを返す (エクスポート用)
+pub fn create_surface_mesh(surface: &definition_schema::Surface) -> Option<Mesh> {
+    let (mesh, _) = surface_geometry(surface.shape.unwrap_or(0), Color4::WHITE, true, false);
+    mesh.map(|mesh| surface_sides(mesh, surface).transformed(&surface_transform(surface)))
+}
+
+// is_reverse_normals と is_two_sided を反映する
+fn surface_sides(mesh: Mesh, surface: &definition_schema::Surface) -> Mesh {
+    let mesh = if surface.is_reverse_normals.unwrap_or(false) {
+        mesh.reversed()
+    } else {
+        mesh
+    };
+    if surface.is_two_sided.unwrap_or(false) {
+        mesh.two_sided()
+    } else {
+        mesh
+    }
+}
+
+fn surface_transform(surface: &definition_schema::Surface) -> Mat4 {
     let rotation = Quat::from_rotation_x(-PI / 2.0 * surface.rotation.unwrap_or(0) as f32);
     let orientation = match surface.orientation {
         Some(1) => Quat::from_rotation_z(PI),
@@ -57,38 +110,89 @@ pub fn create_surface_object(
         }
         None => Vec3::ZERO,
     };
-    let transform_matrix = Some(Mat4::from_rotation_translation(
-        orientation.mul_quat(rotation),
-        translation,
-    ));
+    Mat4::from_rotation_translation(orientation.mul_quat(rotation), translation)
+}
 
-    let shape = surface.shape.unwrap_or(0);
-    let (mesh, line) = if let Some(vertices) = surface_shape(shape) {
+fn surface_geometry(
+    shape: i32,
+    color: Color4,
+    show_surface: bool,
+    show_edge: bool,
+) -> (Option<Mesh>, Option<Vec<Vec3>>) {
+    if !is_known_surface_shape(shape) {
+        return unknown_surface_geometry(show_surface, show_edge);
+    }
+    if let Some(vertices) = surface_shape(shape) {
         (
             show_surface.then(|| {
                 Mesh::signle_color_lh(
                     vertices.clone(),
                     (1..(vertices.len() - 1)).map(|i| [0, i, i + 1]).collect(),
-                    Color4::WHITE,
+                    color,
                 )
             }),
             show_edge.then_some(vertices),
         )
     } else {
-        surface_multiple_color(shape, Color4::WHITE, show_surface, show_edge)
-    };
+        surface_multiple_color(shape, color, show_surface, show_edge)
+    }
+}
+
+// 元のビューアが描いていた形だけを知っているものとする。shape 属性が無い 0 も不明として扱う
+pub fn is_known_surface_shape(shape: i32) -> bool {
+    surface_shape(shape).is_some() || matches!(shape, 3..=5)
+}
 
+// 形がわからない面は面全体を目立つ色で塗る
+fn unknown_surface_geometry(
+    show_surface: bool,
+    show_edge: bool,
+) -> (Option<Mesh>, Option<Vec<Vec3>>) {
+    let vertices = surface_shape(1).unwrap();
     (
-        mesh.map(|mesh| SceneObject::from_mesh(mesh, transform_matrix)),
-        line.map(|positions| {
-            SceneObject::from_line(
-                Line::single_color_lh(positions, Color4::BLACK, 1.0, true),
-                transform_matrix,
+        show_surface.then(|| {
+            Mesh::signle_color_lh(
+                vertices.clone(),
+                vec![[0, 1, 2], [0, 2, 3]],
+                SURFACE_COLOR_UNKNOWN,
             )
         }),
+        show_edge.then_some(vertices),
     )
 }
 
+// 形のわからない面の一覧 (buoyancy_surfaces も含む)
+pub fn surface_shape_warnings(data: &definition_schema::Definition) -> Vec<String> {
+    let layers = [
+        ("surfaces", data.surfaces.last().map(|s| &s.surface)),
+        (
+            "buoyancy_surfaces",
+            data.buoyancy_surfaces.last().map(|s| &s.surface),
+        ),
+    ];
+    let mut warnings = Vec::new();
+    for (layer, surfaces) in layers {
+        for surface in surfaces.into_iter().flatten() {
+            let shape = surface.shape.unwrap_or(0);
+            if !is_known_surface_shape(shape) {
+                let position = surface
+                    .position
+                    .last()
+                    .map(|p| format!("({}, {}, {})", p.x, p.y, p.z))
+                    .unwrap_or_default();
+                warnings.push(format!(
+                    "{}: unknown shape {} at {} orientation {}",
+                    layer,
+                    shape,
+                    position,
+                    surface.orientation.unwrap_or(0)
+                ));
+            }
+        }
+    }
+    warnings
+}
+
 fn surface_shape(shape: i32) -> Option<Vec<Vec3>> {
     Some(match shape {
         1 => vec![
@@ -485,7 +589,7 @@ fn surface_multiple_color(
                             outer_radius,
                             Some(inner_radius),
                             angle_offset,
-                            Color4::WHITE,
+                            color,
                         ),
                     ])
                 }),
@@ -585,3 +689,35 @@ fn regular_polygon_yz(
 
     Mesh::signle_color_lh(vertices, triangles, color)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use definition_schema::{Definition, Surface, Surfaces};
+
+    #[test]
+    fn warns_about_shapes_without_source() {
+        let surface = |shape| Surface {
+            shape,
+            ..Default::default()
+        };
+        let data = Definition {
+            surfaces: vec![Surfaces {
+                surface: vec![
+                    surface(None),
+                    surface(Some(1)),
+                    surface(Some(4)),
+                    surface(Some(999)),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert!((1..=5).all(is_known_surface_shape));
+        assert!(!is_known_surface_shape(0));
+        assert_eq!(surface_shape_warnings(&data).len(), 2);
+        assert!(surface_shape_warnings(&data)[0].contains("unknown shape 0"));
+        assert!(surface_shape_warnings(&data)[1].contains("unknown shape 999"));
+    }
+}
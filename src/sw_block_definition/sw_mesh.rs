@@ -1,28 +1,103 @@
 use crate::gl_renderer;
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use core::fmt;
 use glam::Vec3;
 use std::{
+    collections::BTreeSet,
     fs,
-    io::{self, Cursor, Read},
+    io::{self, Cursor, Read, Write},
+    ops::Range,
     path::Path,
 };
 
 #[derive(Debug)]
 pub struct SwMesh {
-    _mesh_type: SwMeshType,
+    mesh_type: SwMeshType,
+    header0: u16,
+    header1: u16,
     _vertex_count: u16,
+    header3: u16,
+    header4: u16,
     vertices: Vec<SwMeshVertex>,
     _index_count: u32,
     _triangle_count: u32,
     triangles: Vec<SwMeshTriangle>,
     _submesh_count: u16,
     submeshes: Vec<SwSubmesh>,
+    trailing: Vec<u8>,
 }
 
+// 新規に作るファイルのヘッダーはゲーム付属のメッシュと同じ値にする
+const DEFAULT_HEADER0: u16 = 7;
+const DEFAULT_HEADER1: u16 = 1;
+const DEFAULT_HEADER3: u16 = 0x13;
+const DEFAULT_HEADER4: u16 = 0;
+
 impl SwMesh {
+    pub fn new(
+        mesh_type: SwMeshType,
+        vertices: Vec<SwMeshVertex>,
+        triangles: Vec<SwMeshTriangle>,
+        submeshes: Vec<SwSubmesh>,
+    ) -> Result<Self, SwMeshFromFileError> {
+        let vertex_count: u16 = vertices.len().try_into().map_err(|_| {
+            SwMeshFromFileError::Invalid(format!(
+                "Mesh has too many vertices: {} (max {}).",
+                vertices.len(),
+                u16::MAX
+            ))
+        })?;
+        let submesh_count: u16 = submeshes.len().try_into().map_err(|_| {
+            SwMeshFromFileError::Invalid(format!(
+                "Mesh has too many submeshes: {} (max {}).",
+                submeshes.len(),
+                u16::MAX
+            ))
+        })?;
+        if let Some(i) = triangles
+            .iter()
+            .flat_map(|triangle| triangle.indices)
+            .find(|i| *i >= vertex_count)
+        {
+            return Err(SwMeshFromFileError::Invalid(format!(
+                "Triangle index {} is out of range.",
+                i
+            )));
+        }
+        if let Some(submesh) = submeshes
+            .iter()
+            .find(|submesh| submesh.triangle_range().end > triangles.len())
+        {
+            return Err(SwMeshFromFileError::Invalid(format!(
+                "Submesh triangle range {:?} is out of range.",
+                submesh.triangle_range()
+            )));
+        }
+
+        let index_count = triangles.len() as u32 * 3;
+        Ok(Self {
+            mesh_type,
+            header0: DEFAULT_HEADER0,
+            header1: DEFAULT_HEADER1,
+            _vertex_count: vertex_count,
+            header3: DEFAULT_HEADER3,
+            header4: DEFAULT_HEADER4,
+            vertices,
+            _index_count: index_count,
+            _triangle_count: triangles.len() as u32,
+            triangles,
+            _submesh_count: submesh_count,
+            submeshes,
+            trailing: Vec::new(),
+        })
+    }
+
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, SwMeshFromFileError> {
-        let mut cur = Cursor::new(fs::read(path)?);
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, SwMeshFromFileError> {
+        let mut cur = Cursor::new(bytes);
         let data4: &mut [u8] = &mut [0; 4];
 
         cur.read_exact(data4)?;
@@ -40,11 +115,11 @@ impl SwMesh {
             ));
         }
 
-        let _header0 = cur.read_u16::<LittleEndian>()?;
-        let _header1 = cur.read_u16::<LittleEndian>()?;
+        let header0 = cur.read_u16::<LittleEndian>()?;
+        let header1 = cur.read_u16::<LittleEndian>()?;
         let vertex_count = cur.read_u16::<LittleEndian>()?;
-        let _header3 = cur.read_u16::<LittleEndian>()?;
-        let _header4 = cur.read_u16::<LittleEndian>()?;
+        let header3 = cur.read_u16::<LittleEndian>()?;
+        let header4 = cur.read_u16::<LittleEndian>()?;
 
         let mut vertices = Vec::with_capacity(vertex_count.into());
         for _ in 0..vertex_count {
@@ -72,30 +147,104 @@ impl SwMesh {
             submeshes.push(SwSubmesh::from_binary(&mut cur, index_count)?);
         }
 
+        // 未知のデータが続いていても書き戻せるように保持しておく
+        let mut trailing = Vec::new();
+        cur.read_to_end(&mut trailing)?;
+
         Ok(Self {
-            _mesh_type: mesh_type.unwrap(),
+            mesh_type: mesh_type.unwrap(),
+            header0,
+            header1,
             _vertex_count: vertex_count,
+            header3,
+            header4,
             vertices,
             _index_count: index_count,
             _triangle_count: triangle_count,
             triangles,
             _submesh_count: submesh_count,
             submeshes,
+            trailing,
         })
     }
 
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), SwMeshFromFileError> {
+        let vertex_count: u16 = self.vertices.len().try_into().map_err(|_| {
+            SwMeshFromFileError::Write(format!(
+                "Mesh has too many vertices: {} (max {}).",
+                self.vertices.len(),
+                u16::MAX
+            ))
+        })?;
+        let submesh_count: u16 = self.submeshes.len().try_into().map_err(|_| {
+            SwMeshFromFileError::Write(format!(
+                "Mesh has too many submeshes: {} (max {}).",
+                self.submeshes.len(),
+                u16::MAX
+            ))
+        })?;
+
+        w.write_all(self.mesh_type.magic())?;
+        w.write_u16::<LittleEndian>(self.header0)?;
+        w.write_u16::<LittleEndian>(self.header1)?;
+        w.write_u16::<LittleEndian>(vertex_count)?;
+        w.write_u16::<LittleEndian>(self.header3)?;
+        w.write_u16::<LittleEndian>(self.header4)?;
+
+        for vertex in &self.vertices {
+            vertex.write_to(w)?;
+        }
+
+        w.write_u32::<LittleEndian>((self.triangles.len() * 3) as u32)?;
+        for triangle in &self.triangles {
+            triangle.write_to(w)?;
+        }
+
+        w.write_u16::<LittleEndian>(submesh_count)?;
+        for submesh in &self.submeshes {
+            submesh.write_to(w)?;
+        }
+
+        w.write_all(&self.trailing)?;
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SwMeshFromFileError> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), SwMeshFromFileError> {
+        fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn mesh_type(&self) -> SwMeshType {
+        self.mesh_type
+    }
+
+    pub fn vertices(&self) -> &Vec<SwMeshVertex> {
+        &self.vertices
+    }
+
+    pub fn triangles(&self) -> &Vec<SwMeshTriangle> {
+        &self.triangles
+    }
+
+    pub fn submeshes(&self) -> &Vec<SwSubmesh> {
+        &self.submeshes
+    }
+
     pub fn as_meshes(&self) -> Vec<gl_renderer::Mesh> {
         self.submeshes
             .iter()
             .map(|submesh| {
-                let start_index = submesh.index_buffer_start / 3;
-                let end_index = start_index + submesh.index_buffer_length / 3;
-
                 let mut vertices: Vec<gl_renderer::MeshVertex> = Vec::new();
                 let mut triangles = Vec::new();
 
-                for triangle_index in start_index..end_index {
-                    let indices = &self.triangles[triangle_index as usize].as_usize_arr();
+                for triangle in &self.triangles[submesh.triangle_range()] {
+                    let indices = &triangle.as_usize_arr();
                     let vertex_index = vertices.len();
                     for i in indices {
                         vertices.push(self.vertices[*i].as_mesh_vertex());
@@ -104,26 +253,83 @@ impl SwMesh {
                 }
 
                 let mut mesh = gl_renderer::Mesh::new(vertices, triangles);
-                if submesh.shader_id == 1 {
+                if submesh.is_glass() {
                     mesh.glass();
                 }
                 mesh
             })
             .collect()
     }
+
+    // 衝突判定用の形状として、頂点カラーを無視して半透明の単色で表示する
+    pub fn as_physics_mesh(&self, color: gl_renderer::Color4) -> gl_renderer::Mesh {
+        let mut vertices = Vec::with_capacity(self.triangles.len() * 3);
+        let mut triangles = Vec::with_capacity(self.triangles.len());
+
+        for triangle in &self.triangles {
+            let vertex_index = vertices.len();
+            for i in triangle.as_usize_arr() {
+                vertices.push(gl_renderer::MeshVertex {
+                    color,
+                    ..self.vertices[i].as_mesh_vertex()
+                });
+            }
+            triangles.push([vertex_index, vertex_index + 1, vertex_index + 2]);
+        }
+
+        let mut mesh = gl_renderer::Mesh::new(vertices, triangles);
+        mesh.physics();
+        mesh
+    }
+
+    // 三角形の辺を重複なしで線分にする
+    pub fn as_wireframe(&self, color: gl_renderer::Color4, line_width: f32) -> gl_renderer::Line {
+        let edges: BTreeSet<(usize, usize)> = self
+            .triangles
+            .iter()
+            .flat_map(|triangle| {
+                let [a, b, c] = triangle.as_usize_arr();
+                [(a, b), (b, c), (c, a)]
+            })
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect();
+
+        let vertices = edges
+            .into_iter()
+            .flat_map(|(a, b)| [a, b])
+            .map(|i| gl_renderer::LineVertex {
+                position: self.vertices[i].as_mesh_vertex().position,
+                color,
+            })
+            .collect();
+        gl_renderer::Line::new(vertices, line_width)
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SwMeshType {
     Mesh,
     Phys,
 }
 
+impl SwMeshType {
+    fn magic(&self) -> &'static [u8; 4] {
+        match self {
+            Self::Mesh => b"mesh",
+            Self::Phys => b"phys",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SwMeshFromFileError {
     Io(io::Error),
     Utf8(std::str::Utf8Error),
     Parse(String),
+    // ファイルの形式では表せないメッシュを書き出そうとした
+    Write(String),
+    // SwMesh::new や SwSubmesh::new に不正な内容を渡した
+    Invalid(String),
 }
 
 impl fmt::Display for SwMeshFromFileError {
@@ -132,6 +338,8 @@ impl fmt::Display for SwMeshFromFileError {
             Self::Io(err) => err.fmt(f),
             Self::Utf8(err) => err.fmt(f),
             Self::Parse(message) => write!(f, "{}", message),
+            Self::Write(message) => write!(f, "{}", message),
+            Self::Invalid(message) => write!(f, "{}", message),
         }
     }
 }
@@ -167,6 +375,25 @@ impl SwMeshVertex {
         })
     }
 
+    fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.position.write_to(w)?;
+        self.color.write_to(w)?;
+        self.normal.write_to(w)
+    }
+
+    // ビューアの右手系の頂点からゲームの左手系の頂点を作る
+    pub fn from_mesh_vertex(vertex: &gl_renderer::MeshVertex) -> Self {
+        Self {
+            position: SwMeshVec3::from_vec3_rh(vertex.position),
+            color: SwMeshColor4::from_color4(&vertex.color),
+            normal: SwMeshVec3::from_vec3_rh(vertex.normal),
+        }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position.as_vec3()
+    }
+
     pub fn as_mesh_vertex(&self) -> gl_renderer::MeshVertex {
         gl_renderer::MeshVertex {
             position: Vec3::new(self.position.x, self.position.y, -self.position.z),
@@ -182,6 +409,10 @@ pub struct SwMeshTriangle {
 }
 
 impl SwMeshTriangle {
+    pub fn new(indices: [u16; 3]) -> Self {
+        Self { indices }
+    }
+
     fn from_binary(cur: &mut Cursor<Vec<u8>>) -> std::io::Result<Self> {
         let indices = [
             cur.read_u16::<LittleEndian>()?,
@@ -191,6 +422,13 @@ impl SwMeshTriangle {
         Ok(Self { indices })
     }
 
+    fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for i in self.indices {
+            w.write_u16::<LittleEndian>(i)?;
+        }
+        Ok(())
+    }
+
     pub fn as_usize_arr(&self) -> [usize; 3] {
         self.indices.map(|i| i as usize)
     }
@@ -200,14 +438,47 @@ impl SwMeshTriangle {
 pub struct SwSubmesh {
     index_buffer_start: u32,
     index_buffer_length: u32,
+    header2: u16,
     shader_id: u16,
-    _bounds_min: SwMeshVec3,
-    _bounds_max: SwMeshVec3,
+    bounds_min: SwMeshVec3,
+    bounds_max: SwMeshVec3,
+    header6: u16,
     _name_len: u16,
-    _name: Result<String, std::string::FromUtf8Error>,
+    name: Result<String, std::string::FromUtf8Error>,
+    header8: SwMeshVec3,
 }
 
 impl SwSubmesh {
+    // triangles は SwMesh 全体の三角形のうちこのサブメッシュが使う範囲
+    // 名前の長さは u16 で書くので、それを超える名前は受け付けない
+    pub fn new(
+        triangles: Range<usize>,
+        shader_id: u16,
+        bounds_min: Vec3,
+        bounds_max: Vec3,
+        name: String,
+    ) -> Result<Self, SwMeshFromFileError> {
+        let name_len: u16 = name.len().try_into().map_err(|_| {
+            SwMeshFromFileError::Invalid(format!(
+                "Submesh name is too long: {} bytes (max {}).",
+                name.len(),
+                u16::MAX
+            ))
+        })?;
+        Ok(Self {
+            index_buffer_start: (triangles.start * 3) as u32,
+            index_buffer_length: (triangles.len() * 3) as u32,
+            header2: 0,
+            shader_id,
+            bounds_min: SwMeshVec3::from_vec3(bounds_min),
+            bounds_max: SwMeshVec3::from_vec3(bounds_max),
+            header6: 0,
+            _name_len: name_len,
+            name: Ok(name),
+            header8: SwMeshVec3::from_vec3(Vec3::ZERO),
+        })
+    }
+
     fn from_binary(
         cur: &mut Cursor<Vec<u8>>,
         index_count: u32,
@@ -226,27 +497,70 @@ impl SwSubmesh {
                 index_buffer_length
             )));
         }
-        let _header2 = cur.read_u16::<LittleEndian>()?;
+        let header2 = cur.read_u16::<LittleEndian>()?;
         let shader_id = cur.read_u16::<LittleEndian>()?;
         let bounds_min = SwMeshVec3::from_binary(cur)?;
         let bounds_max = SwMeshVec3::from_binary(cur)?;
-        let _header6 = cur.read_u16::<LittleEndian>()?;
+        let header6 = cur.read_u16::<LittleEndian>()?;
         let name_len = cur.read_u16::<LittleEndian>()?;
         let mut name = Vec::with_capacity(name_len.into());
         for _ in 0..name_len {
             name.push(cur.read_u8()?);
         }
-        let _header8 = SwMeshVec3::from_binary(cur)?;
+        let header8 = SwMeshVec3::from_binary(cur)?;
         Ok(Self {
             index_buffer_start,
             index_buffer_length,
+            header2,
             shader_id,
-            _bounds_min: bounds_min,
-            _bounds_max: bounds_max,
+            bounds_min,
+            bounds_max,
+            header6,
             _name_len: name_len,
-            _name: String::from_utf8(name),
+            name: String::from_utf8(name),
+            header8,
         })
     }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> Result<(), SwMeshFromFileError> {
+        // UTF-8 として読めなかった名前も元のバイト列のまま書き戻す
+        let name = match &self.name {
+            Ok(name) => name.as_bytes(),
+            Err(err) => err.as_bytes(),
+        };
+        let name_len: u16 = name.len().try_into().map_err(|_| {
+            SwMeshFromFileError::Write(format!("Submesh name is too long: {} bytes.", name.len()))
+        })?;
+
+        w.write_u32::<LittleEndian>(self.index_buffer_start)?;
+        w.write_u32::<LittleEndian>(self.index_buffer_length)?;
+        w.write_u16::<LittleEndian>(self.header2)?;
+        w.write_u16::<LittleEndian>(self.shader_id)?;
+        self.bounds_min.write_to(w)?;
+        self.bounds_max.write_to(w)?;
+        w.write_u16::<LittleEndian>(self.header6)?;
+        w.write_u16::<LittleEndian>(name_len)?;
+        w.write_all(name)?;
+        self.header8.write_to(w)?;
+        Ok(())
+    }
+
+    pub fn triangle_range(&self) -> Range<usize> {
+        let start = (self.index_buffer_start / 3) as usize;
+        start..(start + (self.index_buffer_length / 3) as usize)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref().ok()
+    }
+
+    pub fn shader_id(&self) -> u16 {
+        self.shader_id
+    }
+
+    pub fn is_glass(&self) -> bool {
+        self.shader_id == 1
+    }
 }
 
 #[derive(Debug)]
@@ -257,12 +571,34 @@ pub struct SwMeshVec3 {
 }
 
 impl SwMeshVec3 {
+    fn from_vec3(v: Vec3) -> Self {
+        Self {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+
+    fn from_vec3_rh(v: Vec3) -> Self {
+        Self::from_vec3(Vec3::new(v.x, v.y, -v.z))
+    }
+
+    fn as_vec3(&self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
     fn from_binary(cur: &mut Cursor<Vec<u8>>) -> std::io::Result<Self> {
         let x = cur.read_f32::<LittleEndian>()?;
         let y = cur.read_f32::<LittleEndian>()?;
         let z = cur.read_f32::<LittleEndian>()?;
         Ok(Self { x, y, z })
     }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_f32::<LittleEndian>(self.x)?;
+        w.write_f32::<LittleEndian>(self.y)?;
+        w.write_f32::<LittleEndian>(self.z)
+    }
 }
 
 #[derive(Debug)]
@@ -274,6 +610,16 @@ pub struct SwMeshColor4 {
 }
 
 impl SwMeshColor4 {
+    fn from_color4(color: &gl_renderer::Color4) -> Self {
+        let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Self {
+            r: to_u8(color.r),
+            g: to_u8(color.g),
+            b: to_u8(color.b),
+            a: to_u8(color.a),
+        }
+    }
+
     fn from_binary(cur: &mut Cursor<Vec<u8>>) -> std::io::Result<Self> {
         let r = cur.read_u8()?;
         let g = cur.read_u8()?;
@@ -282,6 +628,10 @@ impl SwMeshColor4 {
         Ok(Self { r, g, b, a })
     }
 
+    fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&[self.r, self.g, self.b, self.a])
+    }
+
     fn as_color4(&self) -> gl_renderer::Color4 {
         gl_renderer::Color4 {
             r: self.r as f32 / 255.0,
@@ -291,3 +641,136 @@ impl SwMeshColor4 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ヘッダーに既定値以外を入れ、名前の無いサブメッシュと末尾の未知データも含める
+    fn sample_bytes(magic: &[u8; 4]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(magic);
+        for header in [0x1234u16, 0x5678, 3, 0x9abc, 0xdef0] {
+            bytes.write_u16::<LittleEndian>(header).unwrap();
+        }
+        for (i, position) in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, -0.5]]
+            .into_iter()
+            .enumerate()
+        {
+            for v in position {
+                bytes.write_f32::<LittleEndian>(v).unwrap();
+            }
+            bytes.extend_from_slice(&[10 * i as u8, 20, 30, 255]);
+            for v in [0.0f32, 0.0, 1.0] {
+                bytes.write_f32::<LittleEndian>(v).unwrap();
+            }
+        }
+        bytes.write_u32::<LittleEndian>(6).unwrap();
+        for i in [0u16, 1, 2, 2, 1, 0] {
+            bytes.write_u16::<LittleEndian>(i).unwrap();
+        }
+        bytes.write_u16::<LittleEndian>(2).unwrap();
+        for (start, name) in [(0u32, &b"body"[..]), (3, &[0xff, 0xfe][..])] {
+            bytes.write_u32::<LittleEndian>(start).unwrap();
+            bytes.write_u32::<LittleEndian>(3).unwrap();
+            bytes.write_u16::<LittleEndian>(0x11).unwrap();
+            bytes.write_u16::<LittleEndian>(1).unwrap();
+            for v in [0.0f32, 0.0, -0.5, 1.0, 1.0, 0.0] {
+                bytes.write_f32::<LittleEndian>(v).unwrap();
+            }
+            bytes.write_u16::<LittleEndian>(0x22).unwrap();
+            bytes.write_u16::<LittleEndian>(name.len() as u16).unwrap();
+            bytes.extend_from_slice(name);
+            for v in [0.25f32, 0.5, 0.75] {
+                bytes.write_f32::<LittleEndian>(v).unwrap();
+            }
+        }
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5]);
+        bytes
+    }
+
+    #[test]
+    fn round_trip_is_byte_identical() {
+        for magic in [b"mesh", b"phys"] {
+            let bytes = sample_bytes(magic);
+            let mesh = SwMesh::from_bytes(bytes.clone()).unwrap();
+            assert_eq!(mesh.vertices().len(), 3);
+            assert_eq!(mesh.triangles().len(), 2);
+            assert_eq!(mesh.submeshes()[0].name(), Some("body"));
+            assert_eq!(mesh.submeshes()[1].name(), None);
+            assert_eq!(mesh.trailing, [1, 2, 3, 4, 5]);
+            assert_eq!(mesh.to_bytes().unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_magic() {
+        let mut bytes = sample_bytes(b"mesh");
+        bytes[..4].copy_from_slice(b"abcd");
+        assert!(matches!(
+            SwMesh::from_bytes(bytes),
+            Err(SwMeshFromFileError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn new_rejects_out_of_range_index() {
+        let result = SwMesh::new(
+            SwMeshType::Mesh,
+            Vec::new(),
+            vec![SwMeshTriangle::new([0, 1, 2])],
+            Vec::new(),
+        );
+        assert!(matches!(result, Err(SwMeshFromFileError::Invalid(_))));
+    }
+
+    #[test]
+    fn submesh_rejects_too_long_name() {
+        let name = "a".repeat(u16::MAX as usize + 1);
+        let result = SwSubmesh::new(0..0, 0, Vec3::ZERO, Vec3::ZERO, name);
+        assert!(matches!(result, Err(SwMeshFromFileError::Invalid(_))));
+        let name = "a".repeat(u16::MAX as usize);
+        assert!(SwSubmesh::new(0..0, 0, Vec3::ZERO, Vec3::ZERO, name).is_ok());
+    }
+
+    // ROM のメッシュを全て読み書きする。SW_ROM_PATH に ROM フォルダを指定して
+    // cargo test -- --ignored で実行する
+    #[test]
+    #[ignore]
+    fn round_trip_rom_meshes() {
+        let rom_path = std::env::var("SW_ROM_PATH").expect("SW_ROM_PATH is not set");
+        let mut dirs = vec![Path::new(&rom_path).join("meshes")];
+        let mut count = 0;
+        let mut failures = Vec::new();
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                if !path
+                    .extension()
+                    .is_some_and(|ext| ext == "mesh" || ext == "phys")
+                {
+                    continue;
+                }
+                let bytes = fs::read(&path).unwrap();
+                count += 1;
+                match SwMesh::from_bytes(bytes.clone()).and_then(|mesh| mesh.to_bytes()) {
+                    Ok(written) if written == bytes => {}
+                    Ok(_) => failures.push(format!("{}: differs after round trip", path.display())),
+                    Err(err) => failures.push(format!("{}: {}", path.display(), err)),
+                }
+            }
+        }
+        assert!(count > 0, "no meshes found in {}", rom_path);
+        assert!(
+            failures.is_empty(),
+            "{} of {} meshes failed:\n{}",
+            failures.len(),
+            count,
+            failures.join("\n")
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use super::definition_schema::{PhysicsShapeRotation, Position, Voxel};
+use super::marker::{line_segments, to_rh, triangle_mesh};
+use crate::gl_renderer::{Color4, Mesh, SceneObject};
+use glam::{IVec3, Mat3, Vec3};
+
+const VOXEL_SIZE: f32 = 0.25;
+
+const VOXEL_COLOR: Color4 = Color4 {
+    r: 0.6,
+    g: 0.6,
+    b: 0.6,
+    a: 1.0,
+};
+const PHYSICS_SHAPE_COLOR: Color4 = Color4 {
+    r: 0.3,
+    g: 0.8,
+    b: 1.0,
+    a: 0.3,
+};
+const PHYSICS_SHAPE_EDGE_COLOR: Color4 = Color4 {
+    r: 0.3,
+    g: 0.8,
+    b: 1.0,
+    a: 1.0,
+};
+// 形がわからない physics_shape
+const UNKNOWN_SHAPE_COLOR: Color4 = Color4 {
+    r: 1.0,
+    g: 0.5,
+    b: 0.0,
+    a: 0.3,
+};
+
+// ボクセルごとの 0.25m の立方体の枠と、回転させた physics_shape を返す
+// オブジェクト数を抑えるため全ボクセルをまとめて 1 つの線と 1 つのメッシュにする
+pub fn create_voxel_objects(voxels: &[&Voxel]) -> Vec<SceneObject> {
+    let mut cube_edges = Vec::new();
+    let mut shape_edges = Vec::new();
+    let mut shape_meshes = Vec::new();
+
+    for voxel in voxels {
+        let center = voxel_center(voxel.position.last());
+        for (a, b) in box_edges(
+            Vec3::splat(-0.5 * VOXEL_SIZE),
+            Vec3::splat(0.5 * VOXEL_SIZE),
+        ) {
+            cube_edges.push((to_rh(center + a), to_rh(center + b)));
+        }
+
+        let shape = voxel.physics_shape.unwrap_or(0);
+        if shape == 0 {
+            continue;
+        }
+        let (faces, color) = match physics_shape_faces(shape) {
+            Some(faces) => (faces, PHYSICS_SHAPE_COLOR),
+            None => (physics_shape_faces(1).unwrap(), UNKNOWN_SHAPE_COLOR),
+        };
+        let rotation = rotation_matrix(voxel.physics_shape_rotation.last());
+        let transform = |p: Vec3| to_rh(center + rotation.mul_vec3(p));
+
+        let faces: Vec<Vec<Vec3>> = faces
+            .iter()
+            .map(|face| face.iter().map(|&p| transform(p)).collect())
+            .collect();
+        let points: Vec<Vec3> = faces.iter().flatten().copied().collect();
+        let inside = points.iter().sum::<Vec3>() / points.len() as f32;
+
+        let mut triangles = Vec::new();
+        for face in &faces {
+            for i in 0..face.len() {
+                shape_edges.push((face[i], face[(i + 1) % face.len()]));
+            }
+            for i in 1..(face.len() - 1) {
+                triangles.push([face[0], face[i], face[i + 1]]);
+            }
+        }
+        orient_outward(&mut triangles, inside);
+        shape_meshes.push(triangle_mesh(&triangles, color));
+    }
+
+    let mut objects = Vec::new();
+    if !cube_edges.is_empty() {
+        objects.push(SceneObject::from_line(
+            line_segments(&cube_edges, VOXEL_COLOR, 1.0),
+            None,
+        ));
+    }
+    if !shape_meshes.is_empty() {
+        let mut mesh = Mesh::combined(shape_meshes);
+        mesh.physics();
+        objects.push(SceneObject::from_mesh(mesh, None));
+        objects.push(SceneObject::from_line(
+            line_segments(&shape_edges, PHYSICS_SHAPE_EDGE_COLOR, 1.0),
+            None,
+        ));
+    }
+    objects
+}
+
+// voxel_min/max などボクセル座標で表された範囲を囲む箱
+pub fn create_voxel_bounds_object(min: IVec3, max: IVec3, color: Color4) -> SceneObject {
+    let min = VOXEL_SIZE * (min.as_vec3() - Vec3::splat(0.5));
+    let max = VOXEL_SIZE * (max.as_vec3() + Vec3::splat(0.5));
+    let edges: Vec<(Vec3, Vec3)> = box_edges(min, max)
+        .into_iter()
+        .map(|(a, b)| (to_rh(a), to_rh(b)))
+        .collect();
+    SceneObject::from_line(line_segments(&edges, color, 2.0), None)
+}
+
+fn voxel_center(position: Option<&Position>) -> Vec3 {
+    match position {
+        Some(position) => {
+            VOXEL_SIZE * Vec3::new(position.x as f32, position.y as f32, position.z as f32)
+        }
+        None => Vec3::ZERO,
+    }
+}
+
+// 行列の成分 "ij" は i 行 j 列
+fn rotation_matrix(rotation: Option<&PhysicsShapeRotation>) -> Mat3 {
+    match rotation {
+        Some(r) => Mat3::from_cols(
+            Vec3::new(r.r00 as f32, r.r10 as f32, r.r20 as f32),
+            Vec3::new(r.r01 as f32, r.r11 as f32, r.r21 as f32),
+            Vec3::new(r.r02 as f32, r.r12 as f32, r.r22 as f32),
+        ),
+        None => Mat3::IDENTITY,
+    }
+}
+
+// physics_shape の面 (ゲームの左手系、ボクセル中心が原点)
+// 1: 立方体, 2: 斜面 (y <= x の半分), 3: 角 (三角錐)
+// それ以外の番号の形は不明なので None を返す
+fn physics_shape_faces(shape: i32) -> Option<Vec<Vec<Vec3>>> {
+    let h = 0.5 * VOXEL_SIZE;
+    let p = |x: f32, y: f32, z: f32| Vec3::new(x * h, y * h, z * h);
+    Some(match shape {
+        1 => vec![
+            vec![
+                p(1., -1., -1.),
+                p(1., 1., -1.),
+                p(1., 1., 1.),
+                p(1., -1., 1.),
+            ],
+            vec![
+                p(-1., -1., -1.),
+                p(-1., 1., -1.),
+                p(-1., 1., 1.),
+                p(-1., -1., 1.),
+            ],
+            vec![
+                p(-1., 1., -1.),
+                p(1., 1., -1.),
+                p(1., 1., 1.),
+                p(-1., 1., 1.),
+            ],
+            vec![
+                p(-1., -1., -1.),
+                p(1., -1., -1.),
+                p(1., -1., 1.),
+                p(-1., -1., 1.),
+            ],
+            vec![
+                p(-1., -1., 1.),
+                p(1., -1., 1.),
+                p(1., 1., 1.),
+                p(-1., 1., 1.),
+            ],
+            vec![
+                p(-1., -1., -1.),
+                p(1., -1., -1.),
+                p(1., 1., -1.),
+                p(-1., 1., -1.),
+            ],
+        ],
+        2 => vec![
+            vec![
+                p(-1., -1., -1.),
+                p(1., -1., -1.),
+                p(1., -1., 1.),
+                p(-1., -1., 1.),
+            ],
+            vec![
+                p(1., -1., -1.),
+                p(1., 1., -1.),
+                p(1., 1., 1.),
+                p(1., -1., 1.),
+            ],
+            vec![
+                p(-1., -1., -1.),
+                p(1., 1., -1.),
+                p(1., 1., 1.),
+                p(-1., -1., 1.),
+            ],
+            vec![p(-1., -1., -1.), p(1., -1., -1.), p(1., 1., -1.)],
+            vec![p(-1., -1., 1.), p(1., -1., 1.), p(1., 1., 1.)],
+        ],
+        3 => vec![
+            vec![p(-1., -1., -1.), p(1., -1., -1.), p(-1., -1., 1.)],
+            vec![p(-1., -1., -1.), p(1., -1., -1.), p(-1., 1., -1.)],
+            vec![p(-1., -1., -1.), p(-1., -1., 1.), p(-1., 1., -1.)],
+            vec![p(1., -1., -1.), p(-1., -1., 1.), p(-1., 1., -1.)],
+        ],
+        _ => return None,
+    })
+}
+
+// 凸形状の三角形を内側の点から見て表が外を向くように並べ替える
+fn orient_outward(triangles: &mut [[Vec3; 3]], inside: Vec3) {
+    for triangle in triangles {
+        let [p0, p1, p2] = *triangle;
+        if (p1 - p0).cross(p2 - p0).dot(p0 - inside) < 0.0 {
+            triangle.swap(1, 2);
+        }
+    }
+}
+
+fn box_edges(min: Vec3, max: Vec3) -> Vec<(Vec3, Vec3)> {
+    let corner = |i: usize| {
+        Vec3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        )
+    };
+    // 1 ビットだけ異なる頂点同士を結ぶ
+    let mut edges = Vec::new();
+    for i in 0..8 {
+        for bit in [1, 2, 4] {
+            if i & bit == 0 {
+                edges.push((corner(i), corner(i | bit)));
+            }
+        }
+    }
+    edges
+}
@@ -0,0 +1,70 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+// ROM フォルダの data/definitions とメッシュのフォルダを監視し、変更のあったファイルを集める
+// 監視を始められなかったときは何も返さない (開き直せば読み込み直せる)
+pub struct DefinitionWatcher {
+    rom_path: PathBuf,
+    _watcher: Option<notify::RecommendedWatcher>,
+    receiver: mpsc::Receiver<notify::Result<notify::Event>>,
+}
+
+impl DefinitionWatcher {
+    pub fn new<P: AsRef<Path>>(rom_path: P) -> Self {
+        let rom_path = rom_path.as_ref().to_path_buf();
+        let (sender, receiver) = mpsc::channel();
+
+        let watcher = match notify::recommended_watcher(sender) {
+            Ok(mut watcher) => {
+                for (path, mode) in [
+                    (
+                        rom_path.join("data").join("definitions"),
+                        RecursiveMode::NonRecursive,
+                    ),
+                    (rom_path.join("meshes"), RecursiveMode::Recursive),
+                ] {
+                    if path.is_dir() {
+                        if let Err(err) = watcher.watch(&path, mode) {
+                            log::warn!("Failed to watch {}: {}", path.display(), err);
+                        }
+                    }
+                }
+                Some(watcher)
+            }
+            Err(err) => {
+                log::warn!("Failed to create file watcher: {}", err);
+                None
+            }
+        };
+
+        Self {
+            rom_path,
+            _watcher: watcher,
+            receiver,
+        }
+    }
+
+    pub fn rom_path(&self) -> &Path {
+        &self.rom_path
+    }
+
+    // 前回から作成、変更、削除されたファイルのパスを重複なしで返す
+    // 読み込んだだけのイベントは無視する
+    pub fn poll(&self) -> Vec<PathBuf> {
+        let mut paths = BTreeSet::new();
+        for event in self.receiver.try_iter() {
+            match event {
+                Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                    paths.extend(event.paths);
+                }
+                Ok(_) => {}
+                Err(err) => log::warn!("File watcher error: {}", err),
+            }
+        }
+        paths.into_iter().collect()
+    }
+}
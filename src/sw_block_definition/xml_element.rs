@@ -0,0 +1,960 @@
+use quick_xml::events::{BytesStart, Event};
+use serde::{
+    de::{self, Visitor},
+    ser::{self, Impossible},
+    Deserialize, Serialize,
+};
+use std::fmt::{self, Write};
+
+// quick-xml のシリアライザは None の属性を空文字列で出力し、"00" のような属性名も
+// 受け付けないので、ゲームのファイルと同じ形式で書き出すための最小限のシリアライザを用意する
+//
+// "@" で始まるフィールドは属性、それ以外は子要素として扱う
+// None の属性と空の Vec は出力しない
+//
+// 読み込みは一度 XmlElement の木にしてから、同じ規則のデシリアライザで構造体にする
+// スキーマに無い属性と子要素、テキストは "$extra" フィールドの ExtraXml に集め、書き出すときに戻す
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XmlElement {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlElement>,
+    // 前後の空白を除いたテキスト。子要素の間のインデントは含まない
+    pub text: String,
+}
+
+// スキーマに無い属性と子要素、テキスト
+// ゲームの更新で増えたものも書き出すときに失われないように保持する
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ExtraXml {
+    pub attributes: Vec<(String, String)>,
+    pub elements: Vec<XmlElement>,
+    pub text: String,
+}
+
+// 構造体のこの名前のフィールドに ExtraXml を読み書きする
+pub const EXTRA_FIELD: &str = "$extra";
+
+impl ExtraXml {
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty() && self.elements.is_empty() && self.text.is_empty()
+    }
+
+    // シリアライザとデシリアライザの間では <extra> 要素の文字列として受け渡す
+    fn to_fragment(&self) -> String {
+        let element = XmlElement {
+            name: "extra".to_string(),
+            attributes: self.attributes.clone(),
+            children: self.elements.clone(),
+            text: self.text.clone(),
+        };
+        let mut xml = String::new();
+        element.write(&mut xml, 0);
+        xml
+    }
+
+    fn from_fragment(xml: &str) -> Result<Self, quick_xml::Error> {
+        let element = XmlElement::parse_document(xml)?;
+        Ok(Self {
+            attributes: element.attributes,
+            elements: element.children,
+            text: element.text,
+        })
+    }
+}
+
+impl Serialize for ExtraXml {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_fragment())
+    }
+}
+
+impl<'de> Deserialize<'de> for ExtraXml {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FragmentVisitor;
+
+        impl Visitor<'_> for FragmentVisitor {
+            type Value = ExtraXml;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "an <extra> element")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                ExtraXml::from_fragment(value).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(FragmentVisitor)
+    }
+}
+
+impl XmlElement {
+    pub fn from_serialize<T: Serialize>(name: &str, value: &T) -> Result<Self, XmlWriteError> {
+        value
+            .serialize(ElementSerializer { name })?
+            .pop()
+            .ok_or_else(|| XmlWriteError(format!("<{}> has no value", name)))
+    }
+
+    pub fn parse_document(xml: &str) -> Result<Self, quick_xml::Error> {
+        let mut reader = quick_xml::Reader::from_str(xml);
+        let mut stack: Vec<XmlElement> = Vec::new();
+
+        loop {
+            let element = match reader.read_event()? {
+                Event::Start(e) => {
+                    stack.push(Self::from_start(&e)?);
+                    continue;
+                }
+                Event::Text(e) => {
+                    if let Some(parent) = stack.last_mut() {
+                        parent.text.push_str(e.unescape()?.trim());
+                    }
+                    continue;
+                }
+                Event::CData(e) => {
+                    if let Some(parent) = stack.last_mut() {
+                        parent.text.push_str(&String::from_utf8_lossy(&e));
+                    }
+                    continue;
+                }
+                Event::Empty(e) => Self::from_start(&e)?,
+                Event::End(_) => match stack.pop() {
+                    Some(element) => element,
+                    None => continue,
+                },
+                Event::Eof => {
+                    let name = stack.pop().map(|element| element.name).unwrap_or_default();
+                    return Err(quick_xml::Error::IllFormed(
+                        quick_xml::errors::IllFormedError::MissingEndTag(name),
+                    ));
+                }
+                _ => continue,
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(element),
+                None => return Ok(element),
+            }
+        }
+    }
+
+    fn from_start(e: &BytesStart<'_>) -> Result<Self, quick_xml::Error> {
+        let mut attributes = Vec::new();
+        for attribute in e.attributes() {
+            let attribute = attribute?;
+            attributes.push((
+                String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+                attribute.unescape_value()?.into_owned(),
+            ));
+        }
+        Ok(Self {
+            name: String::from_utf8_lossy(e.name().as_ref()).into_owned(),
+            attributes,
+            ..Default::default()
+        })
+    }
+
+    // 先頭の XML 宣言を含めて、タブでインデントした文字列にする
+    pub fn to_document(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        self.write(&mut xml, 0);
+        xml
+    }
+
+    fn write(&self, xml: &mut String, depth: usize) {
+        for _ in 0..depth {
+            xml.push('\t');
+        }
+        xml.push('<');
+        xml.push_str(&self.name);
+        for (name, value) in &self.attributes {
+            let _ = write!(xml, " {}=\"{}\"", name, escape(value));
+        }
+        if self.children.is_empty() && self.text.is_empty() {
+            xml.push_str("/>\n");
+        } else if self.children.is_empty() {
+            let _ = writeln!(xml, ">{}</{}>", escape(&self.text), self.name);
+        } else {
+            xml.push('>');
+            xml.push_str(&escape(&self.text));
+            xml.push('\n');
+            for child in &self.children {
+                child.write(xml, depth + 1);
+            }
+            for _ in 0..depth {
+                xml.push('\t');
+            }
+            let _ = writeln!(xml, "</{}>", self.name);
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#10;"),
+            // 属性値の正規化で空白に変わらないように文字参照にする
+            '\t' => escaped.push_str("&#9;"),
+            '\r' => escaped.push_str("&#13;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[derive(Debug, Clone)]
+pub struct XmlWriteError(String);
+
+impl fmt::Display for XmlWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for XmlWriteError {}
+
+impl ser::Error for XmlWriteError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T, XmlWriteError> {
+    Err(XmlWriteError(format!("{} is not supported", what)))
+}
+
+macro_rules! unsupported_methods {
+    ($($method:ident($($arg:ty),*) -> $ret:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ret, Self::Error> {
+                unsupported(stringify!($method))
+            }
+        )*
+    };
+}
+
+// 要素として書き出す値
+// Vec は同じ名前の要素の並びになり、None は何も出力しない
+struct ElementSerializer<'a> {
+    name: &'a str,
+}
+
+impl<'a> ser::Serializer for ElementSerializer<'a> {
+    type Ok = Vec<XmlElement>;
+    type Error = XmlWriteError;
+    type SerializeSeq = SeqSerializer<'a>;
+    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = Impossible<Self::Ok, Self::Error>;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Vec::new())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(vec![XmlElement {
+            name: self.name.to_string(),
+            ..Default::default()
+        }])
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(vec![XmlElement {
+            name: self.name.to_string(),
+            text: value.to_string(),
+            ..Default::default()
+        }])
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SeqSerializer {
+            name: self.name,
+            elements: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(StructSerializer {
+            element: XmlElement {
+                name: self.name.to_string(),
+                ..Default::default()
+            },
+        })
+    }
+
+    unsupported_methods! {
+        serialize_bool(bool) -> Self::Ok;
+        serialize_i8(i8) -> Self::Ok;
+        serialize_i16(i16) -> Self::Ok;
+        serialize_i32(i32) -> Self::Ok;
+        serialize_i64(i64) -> Self::Ok;
+        serialize_u8(u8) -> Self::Ok;
+        serialize_u16(u16) -> Self::Ok;
+        serialize_u32(u32) -> Self::Ok;
+        serialize_u64(u64) -> Self::Ok;
+        serialize_f32(f32) -> Self::Ok;
+        serialize_f64(f64) -> Self::Ok;
+        serialize_char(char) -> Self::Ok;
+        serialize_bytes(&[u8]) -> Self::Ok;
+        serialize_unit_variant(&'static str, u32, &'static str) -> Self::Ok;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        unsupported("serialize_newtype_variant")
+    }
+}
+
+struct SeqSerializer<'a> {
+    name: &'a str,
+    elements: Vec<XmlElement>,
+}
+
+impl ser::SerializeSeq for SeqSerializer<'_> {
+    type Ok = Vec<XmlElement>;
+    type Error = XmlWriteError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.elements
+            .extend(value.serialize(ElementSerializer { name: self.name })?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.elements)
+    }
+}
+
+struct StructSerializer {
+    element: XmlElement,
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Vec<XmlElement>;
+    type Error = XmlWriteError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        if key == EXTRA_FIELD {
+            if let Some(fragment) = value.serialize(AttributeSerializer)? {
+                let extra = ExtraXml::from_fragment(&fragment)
+                    .map_err(|err| XmlWriteError(err.to_string()))?;
+                // 編集で同じ名前の属性が追加されていたらそちらを優先する
+                for (name, value) in extra.attributes {
+                    if !self.element.attributes.iter().any(|(n, _)| *n == name) {
+                        self.element.attributes.push((name, value));
+                    }
+                }
+                self.element.children.extend(extra.elements);
+                self.element.text.push_str(&extra.text);
+            }
+        } else if let Some(name) = key.strip_prefix('@') {
+            if let Some(value) = value.serialize(AttributeSerializer)? {
+                self.element.attributes.push((name.to_string(), value));
+            }
+        } else {
+            self.element
+                .children
+                .extend(value.serialize(ElementSerializer { name: key })?);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(vec![self.element])
+    }
+}
+
+// 属性値として書き出す値
+// 数値は Rust の Display と同じく "1" や "0.25" のような最短の表記になる
+struct AttributeSerializer;
+
+macro_rules! serialize_display {
+    ($($method:ident($type:ty);)*) => {
+        $(
+            fn $method(self, value: $type) -> Result<Self::Ok, Self::Error> {
+                Ok(Some(value.to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for AttributeSerializer {
+    type Ok = Option<String>;
+    type Error = XmlWriteError;
+    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = Impossible<Self::Ok, Self::Error>;
+    type SerializeStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    serialize_display! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(variant.to_string()))
+    }
+
+    unsupported_methods! {
+        serialize_bytes(&[u8]) -> Self::Ok;
+        serialize_unit() -> Self::Ok;
+        serialize_unit_struct(&'static str) -> Self::Ok;
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        unsupported("serialize_newtype_variant")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum XmlReadError {
+    // XML として読めない
+    Xml(String),
+    // スキーマに合わない
+    De(String),
+}
+
+impl fmt::Display for XmlReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Xml(message) | Self::De(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for XmlReadError {}
+
+impl de::Error for XmlReadError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::De(msg.to_string())
+    }
+}
+
+impl From<quick_xml::Error> for XmlReadError {
+    fn from(value: quick_xml::Error) -> Self {
+        Self::Xml(value.to_string())
+    }
+}
+
+// XmlElement の木を構造体にする
+pub fn from_element<'de, T: Deserialize<'de>>(element: &'de XmlElement) -> Result<T, XmlReadError> {
+    T::deserialize(ElementDeserializer { element })
+}
+
+macro_rules! forward_methods {
+    ($target:expr; $($method:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                $target(self)?.$method(visitor)
+            }
+        )*
+    };
+}
+
+// 要素。構造体なら属性と子要素を、それ以外ならテキストを読む
+struct ElementDeserializer<'de> {
+    element: &'de XmlElement,
+}
+
+impl<'de> ElementDeserializer<'de> {
+    fn text(self) -> Result<TextDeserializer<'de>, XmlReadError> {
+        Ok(TextDeserializer(&self.element.text))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ElementDeserializer<'de> {
+    type Error = XmlReadError;
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(ElementMapAccess::new(self.element, fields)?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_methods! {
+        Self::text;
+        deserialize_any, deserialize_bool, deserialize_i8, deserialize_i16, deserialize_i32,
+        deserialize_i64, deserialize_u8, deserialize_u16, deserialize_u32, deserialize_u64,
+        deserialize_f32, deserialize_f64, deserialize_char, deserialize_str, deserialize_string,
+        deserialize_unit, deserialize_ignored_any
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf seq tuple tuple_struct map enum identifier
+    }
+}
+
+// 同じ名前の子要素の並び。Vec なら全て、それ以外なら 1 つだけのときにその要素を読む
+struct ElementsDeserializer<'de> {
+    elements: Vec<&'de XmlElement>,
+}
+
+impl<'de> ElementsDeserializer<'de> {
+    fn single(self) -> Result<ElementDeserializer<'de>, XmlReadError> {
+        match self.elements.as_slice() {
+            [element] => Ok(ElementDeserializer { element }),
+            elements => Err(XmlReadError::De(format!(
+                "<{}> appears {} times",
+                elements.first().map_or("", |e| e.name.as_str()),
+                elements.len()
+            ))),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ElementsDeserializer<'de> {
+    type Error = XmlReadError;
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(de::value::SeqDeserializer::new(
+            self.elements
+                .into_iter()
+                .map(|element| ElementDeserializer { element }),
+        ))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_unit_struct(name, visitor)
+    }
+
+    forward_methods! {
+        Self::single;
+        deserialize_any, deserialize_bool, deserialize_i8, deserialize_i16, deserialize_i32,
+        deserialize_i64, deserialize_u8, deserialize_u16, deserialize_u32, deserialize_u64,
+        deserialize_f32, deserialize_f64, deserialize_char, deserialize_str, deserialize_string,
+        deserialize_unit, deserialize_ignored_any
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf tuple tuple_struct map enum identifier
+    }
+}
+
+impl<'de> de::IntoDeserializer<'de, XmlReadError> for ElementDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+enum FieldValue<'de> {
+    Attribute(&'de str),
+    Elements(Vec<&'de XmlElement>),
+    Extra(String),
+}
+
+// 要素の属性と子要素をフィールド名と値の組として渡す
+struct ElementMapAccess<'de> {
+    entries: std::vec::IntoIter<(String, FieldValue<'de>)>,
+    value: Option<FieldValue<'de>>,
+}
+
+impl<'de> ElementMapAccess<'de> {
+    fn new(
+        element: &'de XmlElement,
+        fields: &'static [&'static str],
+    ) -> Result<Self, XmlReadError> {
+        let mut entries = Vec::new();
+        let mut extra = ExtraXml {
+            text: element.text.clone(),
+            ..Default::default()
+        };
+
+        // 空の属性は以前の読み込みと同じく None として扱い、書き出すときのために元の値を残す
+        for (name, value) in &element.attributes {
+            let key = format!("@{}", name);
+            if fields.contains(&key.as_str()) && !value.is_empty() {
+                entries.push((key, FieldValue::Attribute(value.as_str())));
+            } else {
+                extra.attributes.push((name.clone(), value.clone()));
+            }
+        }
+
+        let mut groups: Vec<(&str, Vec<&XmlElement>)> = Vec::new();
+        for child in &element.children {
+            if !fields.contains(&child.name.as_str()) {
+                extra.elements.push(child.clone());
+            } else if let Some((_, group)) = groups.iter_mut().find(|(name, _)| *name == child.name)
+            {
+                group.push(child);
+            } else {
+                groups.push((&child.name, vec![child]));
+            }
+        }
+        entries.extend(
+            groups
+                .into_iter()
+                .map(|(name, group)| (name.to_string(), FieldValue::Elements(group))),
+        );
+
+        if !extra.is_empty() {
+            if !fields.contains(&EXTRA_FIELD) {
+                return Err(XmlReadError::De(format!(
+                    "<{}> has content that cannot be kept",
+                    element.name
+                )));
+            }
+            entries.push((
+                EXTRA_FIELD.to_string(),
+                FieldValue::Extra(extra.to_fragment()),
+            ));
+        }
+
+        Ok(Self {
+            entries: entries.into_iter(),
+            value: None,
+        })
+    }
+}
+
+impl<'de> de::MapAccess<'de> for ElementMapAccess<'de> {
+    type Error = XmlReadError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        seed.deserialize(de::value::StringDeserializer::new(key))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value.take() {
+            Some(FieldValue::Attribute(value)) => seed.deserialize(TextDeserializer(value)),
+            Some(FieldValue::Elements(elements)) => {
+                seed.deserialize(ElementsDeserializer { elements })
+            }
+            Some(FieldValue::Extra(fragment)) => {
+                seed.deserialize(de::value::StringDeserializer::new(fragment))
+            }
+            None => Err(XmlReadError::De("value is missing".to_string())),
+        }
+    }
+}
+
+// 属性値と要素のテキスト。要求された型として解釈する
+struct TextDeserializer<'de>(&'de str);
+
+impl TextDeserializer<'_> {
+    fn parse<T: std::str::FromStr>(&self, type_name: &str) -> Result<T, XmlReadError> {
+        self.0
+            .trim()
+            .parse()
+            .map_err(|_| XmlReadError::De(format!("{:?} is not a valid {}", self.0, type_name)))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident($type:ty);)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.parse::<$type>(stringify!($type))?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for TextDeserializer<'de> {
+    type Error = XmlReadError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0.trim() {
+            "true" | "1" => visitor.visit_bool(true),
+            "false" | "0" => visitor.visit_bool(false),
+            _ => Err(XmlReadError::De(format!(
+                "{:?} is not a valid bool",
+                self.0
+            ))),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8(i8);
+        deserialize_i16 => visit_i16(i16);
+        deserialize_i32 => visit_i32(i32);
+        deserialize_i64 => visit_i64(i64);
+        deserialize_u8 => visit_u8(u8);
+        deserialize_u16 => visit_u16(u16);
+        deserialize_u32 => visit_u32(u32);
+        deserialize_u64 => visit_u64(u64);
+        deserialize_f32 => visit_f32(f32);
+        deserialize_f64 => visit_f64(f64);
+        deserialize_char => visit_char(char);
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 str string bytes byte_buf unit_struct seq tuple tuple_struct map struct
+        enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Child {
+        #[serde(rename = "@x")]
+        x: i32,
+    }
+
+    #[derive(Serialize)]
+    struct Sample {
+        #[serde(rename = "@name")]
+        name: Option<String>,
+        #[serde(rename = "@missing")]
+        missing: Option<i32>,
+        #[serde(rename = "@scale")]
+        scale: f32,
+        #[serde(rename = "@enabled")]
+        enabled: bool,
+        child: Vec<Child>,
+        empty: Vec<Child>,
+        marker: (),
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            name: Some("a".to_string()),
+            missing: None,
+            scale: 0.25,
+            enabled: true,
+            child: vec![Child { x: 1 }, Child { x: -2 }],
+            empty: Vec::new(),
+            marker: (),
+        }
+    }
+
+    #[test]
+    fn writes_fields_in_declaration_order() {
+        let element = XmlElement::from_serialize("sample", &sample()).unwrap();
+        assert_eq!(
+            element.to_document(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <sample name=\"a\" scale=\"0.25\" enabled=\"true\">\n\
+             \t<child x=\"1\"/>\n\
+             \t<child x=\"-2\"/>\n\
+             \t<marker/>\n\
+             </sample>\n"
+        );
+    }
+
+    #[test]
+    fn formats_floats_without_trailing_zeros() {
+        for (value, text) in [
+            (1.0f32, "1"),
+            (0.1, "0.1"),
+            (-0.5, "-0.5"),
+            (0.0000001, "0.0000001"),
+        ] {
+            let child = XmlElement::from_serialize(
+                "sample",
+                &Sample {
+                    scale: value,
+                    ..sample()
+                },
+            )
+            .unwrap();
+            assert_eq!(child.attributes[1], ("scale".to_string(), text.to_string()));
+        }
+    }
+
+    #[test]
+    fn escapes_and_parses_back_attribute_values() {
+        let value = "a&b <c> \"d\" 'e'\n\tf\r";
+        let element = XmlElement {
+            name: "e".to_string(),
+            attributes: vec![("v".to_string(), value.to_string())],
+            ..Default::default()
+        };
+        let xml = element.to_document();
+        assert!(
+            xml.contains("v=\"a&amp;b &lt;c&gt; &quot;d&quot; 'e'&#10;&#9;f&#13;\""),
+            "{}",
+            xml
+        );
+        assert_eq!(XmlElement::parse_document(&xml).unwrap(), element);
+    }
+
+    #[test]
+    fn parses_nested_elements_and_keeps_text() {
+        let root = XmlElement::parse_document(
+            "<?xml version=\"1.0\"?><a x=\"1\">text<b/><c><d y=\"2\"/></c></a>",
+        )
+        .unwrap();
+        assert_eq!(root.name, "a");
+        assert_eq!(root.attributes, [("x".to_string(), "1".to_string())]);
+        assert_eq!(root.text, "text");
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[1].children[0].name, "d");
+        assert!(XmlElement::parse_document("<a><b></a>").is_err());
+    }
+}
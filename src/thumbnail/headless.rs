@@ -0,0 +1,61 @@
+use eframe::glow;
+use glutin::{
+    api::egl::{context::PossiblyCurrentContext, device::Device, display::Display},
+    config::{ConfigSurfaceTypes, ConfigTemplateBuilder},
+    context::{ContextApi, ContextAttributesBuilder, Version},
+    prelude::*,
+};
+use std::{ffi::CString, sync::Arc};
+
+// ウィンドウを作らずに使える OpenGL コンテキスト
+// EGL のデバイス列挙とサーフェスなしのコンテキストを使うので、GPU が無くても
+// Mesa のソフトウェアレンダラがあれば動く
+pub struct HeadlessGl {
+    gl: Arc<glow::Context>,
+    _context: PossiblyCurrentContext,
+    _display: Display,
+}
+
+impl HeadlessGl {
+    pub fn new() -> Result<Self, String> {
+        let device = Device::query_devices()
+            .map_err(|err| format!("Failed to query EGL devices: {}", err))?
+            .next()
+            .ok_or("No EGL device found")?;
+        let display = unsafe { Display::with_device(&device, None) }
+            .map_err(|err| format!("Failed to open EGL display: {}", err))?;
+
+        let template = ConfigTemplateBuilder::new()
+            .with_surface_type(ConfigSurfaceTypes::empty())
+            .build();
+        let config = unsafe { display.find_configs(template) }
+            .map_err(|err| format!("Failed to find EGL config: {}", err))?
+            .next()
+            .ok_or("No EGL config found")?;
+
+        let attributes = ContextAttributesBuilder::new()
+            .with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
+            .build(None);
+        let context = unsafe { display.create_context(&config, &attributes) }
+            .map_err(|err| format!("Failed to create OpenGL context: {}", err))?
+            .make_current_surfaceless()
+            .map_err(|err| format!("Failed to make OpenGL context current: {}", err))?;
+
+        let gl = unsafe {
+            glow::Context::from_loader_function(|name| {
+                let name = CString::new(name).unwrap();
+                display.get_proc_address(&name)
+            })
+        };
+
+        Ok(Self {
+            gl: Arc::new(gl),
+            _context: context,
+            _display: display,
+        })
+    }
+
+    pub fn gl(&self) -> Arc<glow::Context> {
+        self.gl.clone()
+    }
+}
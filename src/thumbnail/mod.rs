@@ -0,0 +1,262 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+#[cfg(not(target_arch = "wasm32"))]
+pub use headless::HeadlessGl;
+
+use crate::gl_renderer::{
+    Color4, MultisampleFramebuffer, OrbitCamera, Scene, SceneObject, SceneRenderer,
+};
+use crate::sw_block_definition::{
+    create_surface_mesh, create_surface_object, SwBlockDefinition, SwBlockDefinitionDataError,
+    SwMeshType,
+};
+use eframe::glow;
+use egui::mutex::Mutex;
+use glam::Vec3;
+use image::RgbaImage;
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+const SAMPLES: i32 = 8;
+const FOV_Y_DEGREES: f32 = 20.0;
+
+// 定義ごとに見た目のメッシュと面を斜め上から描画して画像にする
+pub struct ThumbnailRenderer {
+    gl: Arc<glow::Context>,
+    scene: Arc<Mutex<Scene>>,
+    renderer: SceneRenderer,
+    framebuffer: MultisampleFramebuffer,
+    size: u32,
+}
+
+#[derive(Debug)]
+pub enum ThumbnailError {
+    Data(SwBlockDefinitionDataError),
+    Image(image::ImageError),
+    Io(io::Error),
+}
+
+impl ThumbnailRenderer {
+    pub fn new(gl: Arc<glow::Context>, size: u32) -> Self {
+        let scene = Arc::new(Mutex::new(Scene::default()));
+        let renderer = SceneRenderer::new(&gl, scene.clone());
+        let framebuffer =
+            MultisampleFramebuffer::new(gl.clone(), size as i32, size as i32, SAMPLES)
+                .expect("Failed to create framebuffer");
+        Self {
+            gl,
+            scene,
+            renderer,
+            framebuffer,
+            size,
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn render(
+        &mut self,
+        definition: &mut SwBlockDefinition,
+    ) -> Result<RgbaImage, ThumbnailError> {
+        let objects = definition_objects(definition)?;
+        let camera = fit_camera(&objects.1);
+        {
+            let mut scene = self.scene.lock();
+            scene.clear();
+            for object in objects.0 {
+                scene.add_object(object);
+            }
+        }
+
+        // 背景は透明にする
+        Ok(self.renderer.paint_offscreen(
+            &self.gl,
+            Arc::new(Mutex::new(camera)),
+            &self.framebuffer,
+            Color4::new(0.0, 0.0, 0.0, 0.0),
+        ))
+    }
+
+    // <ファイル名>.png を output_dir に書き出す
+    pub fn render_to_dir<P: AsRef<Path>>(
+        &mut self,
+        definition: &mut SwBlockDefinition,
+        output_dir: P,
+    ) -> Result<(), ThumbnailError> {
+        let image = self.render(definition)?;
+        let stem = definition.path().file_stem().unwrap_or_default();
+        let path = output_dir
+            .as_ref()
+            .join(Path::new(stem).with_extension("png"));
+        image.save(path)?;
+        Ok(())
+    }
+
+    pub fn destroy(&self) {
+        self.renderer.destroy(Some(&self.gl));
+    }
+}
+
+// 全ての定義のサムネイルを書き出し、失敗した定義のファイル名とエラーを返す
+pub fn render_thumbnails<P: AsRef<Path>>(
+    renderer: &mut ThumbnailRenderer,
+    definitions: &mut [SwBlockDefinition],
+    output_dir: P,
+) -> Result<Vec<(String, ThumbnailError)>, ThumbnailError> {
+    let mut job = ThumbnailJob::new(output_dir)?;
+    while !job.is_finished(definitions) {
+        job.render_next(renderer, definitions, None);
+    }
+    Ok(job.into_errors())
+}
+
+// 定義を先頭から 1 件ずつ書き出す。画面を止めないように描画フレームごとに少しずつ進める
+// 書き出した定義のメッシュは捨て、全ての ROM のメッシュがメモリに残らないようにする
+pub struct ThumbnailJob {
+    output_dir: PathBuf,
+    next: usize,
+    errors: Vec<(String, ThumbnailError)>,
+}
+
+impl ThumbnailJob {
+    pub fn new<P: AsRef<Path>>(output_dir: P) -> Result<Self, ThumbnailError> {
+        fs::create_dir_all(output_dir.as_ref())?;
+        Ok(Self {
+            output_dir: output_dir.as_ref().to_path_buf(),
+            next: 0,
+            errors: Vec::new(),
+        })
+    }
+
+    // 書き出し終わった数
+    pub fn done_count(&self) -> usize {
+        self.next
+    }
+
+    pub fn is_finished(&self, definitions: &[SwBlockDefinition]) -> bool {
+        self.next >= definitions.len()
+    }
+
+    // 次の定義を 1 件書き出す。keep_meshes の定義 (表示中のもの) はメッシュを残す
+    pub fn render_next(
+        &mut self,
+        renderer: &mut ThumbnailRenderer,
+        definitions: &mut [SwBlockDefinition],
+        keep_meshes: Option<usize>,
+    ) {
+        let index = self.next;
+        let Some(definition) = definitions.get_mut(index) else {
+            return;
+        };
+        if let Err(err) = renderer.render_to_dir(definition, &self.output_dir) {
+            self.errors.push((definition.filename(), err));
+        }
+        if keep_meshes != Some(index) {
+            definition.unload_meshes();
+        }
+        self.next += 1;
+    }
+
+    pub fn errors(&self) -> &[(String, ThumbnailError)] {
+        &self.errors
+    }
+
+    pub fn into_errors(self) -> Vec<(String, ThumbnailError)> {
+        self.errors
+    }
+}
+
+// 描画するオブジェクトと、カメラを合わせるための頂点
+fn definition_objects(
+    definition: &mut SwBlockDefinition,
+) -> Result<(Vec<SceneObject>, Vec<Vec3>), SwBlockDefinitionDataError> {
+    let data = definition.data()?;
+    let meshes = definition.meshes();
+    let mut objects = Vec::new();
+    let mut points = Vec::new();
+
+    for (_, mesh) in meshes.iter() {
+        if let Ok(mesh) = mesh {
+            if mesh.mesh_type() == SwMeshType::Phys {
+                continue;
+            }
+            for m in mesh.as_meshes() {
+                points.extend(m.vertices().iter().map(|v| v.position));
+                objects.push(SceneObject::from_mesh(m, None));
+            }
+        }
+    }
+
+    if let Some(surfaces) = data.surfaces.last() {
+        for surface in &surfaces.surface {
+            if let Some(mesh) = create_surface_mesh(surface) {
+                points.extend(mesh.vertices().iter().map(|v| v.position));
+            }
+            let (mesh_obj, _) = create_surface_object(surface, Color4::WHITE, true, false);
+            objects.extend(mesh_obj);
+        }
+    }
+
+    Ok((objects, points))
+}
+
+// 全ての頂点が収まるように斜め上から見るカメラを置く
+fn fit_camera(points: &[Vec3]) -> OrbitCamera {
+    let (min, max) = points.iter().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), p| (min.min(*p), max.max(*p)),
+    );
+    let (center, radius) = if points.is_empty() {
+        (Vec3::ZERO, 0.25)
+    } else {
+        ((min + max) / 2.0, ((max - min).length() / 2.0).max(0.05))
+    };
+
+    let fov_y = FOV_Y_DEGREES.to_radians();
+    let distance = 1.05 * radius / (fov_y / 2.0).sin();
+    let mut camera = OrbitCamera {
+        center,
+        direction: distance * Vec3::new(1.0, -1.0, -1.0).normalize(),
+        fov_y,
+        near_clip: (distance - 2.0 * radius).max(0.01),
+        far_clip: distance + 2.0 * radius,
+        ..Default::default()
+    };
+    camera.orthogonalize_up();
+    camera
+}
+
+impl From<SwBlockDefinitionDataError> for ThumbnailError {
+    fn from(value: SwBlockDefinitionDataError) -> Self {
+        Self::Data(value)
+    }
+}
+
+impl From<image::ImageError> for ThumbnailError {
+    fn from(value: image::ImageError) -> Self {
+        Self::Image(value)
+    }
+}
+
+impl From<io::Error> for ThumbnailError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl fmt::Display for ThumbnailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Data(err) => err.fmt(f),
+            Self::Image(err) => err.fmt(f),
+            Self::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ThumbnailError {}
@@ -6,6 +6,8 @@ pub struct MainApp {
     definition_detail_panel: DefinitionDetailPanel,
     definition_3d_panel: Definition3dPanel,
     bottom_panel: BottomPanel,
+    #[cfg(not(target_arch = "wasm32"))]
+    thumbnail_task: Option<ThumbnailTask>,
 }
 
 impl MainApp {
@@ -43,6 +45,8 @@ impl MainApp {
             definition_detail_panel: DefinitionDetailPanel::default(),
             definition_3d_panel: Definition3dPanel::new(cc).unwrap(),
             bottom_panel: BottomPanel::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            thumbnail_task: None,
         }
     }
 }
@@ -58,6 +62,54 @@ impl eframe::App for MainApp {
 
     #[allow(unused_variables)]
     fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
+        let selected = self
+            .state
+            .selected_definition_index()
+            .and_then(|i| self.state.definitions().get(i));
+        let can_save = selected.is_some_and(|definition| definition.is_dirty());
+        let can_undo = selected.is_some_and(|definition| definition.can_undo());
+        let can_redo = selected.is_some_and(|definition| definition.can_redo());
+
+        // テキスト入力中は egui 側の元に戻す操作を優先する
+        if ctx.memory(|memory| memory.focused().is_none()) {
+            let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+            let redo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+            let redo_shift = egui::KeyboardShortcut::new(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::Z,
+            );
+            if ctx.input_mut(|i| i.consume_shortcut(&redo) || i.consume_shortcut(&redo_shift)) {
+                self.state.redo();
+            } else if ctx.input_mut(|i| i.consume_shortcut(&undo)) {
+                self.state.undo();
+            }
+        }
+
+        // ROM フォルダのファイルが書き換えられたら読み込み直す
+        // 操作が無くても変更に気付けるように定期的に描画し直す
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.state.update_watching();
+            ctx.request_repaint_after(std::time::Duration::from_millis(500));
+        }
+
+        // 定義はワーカースレッドで読み込むので、終わるまで描画し直す
+        if self.state.update_loading() {
+            ctx.request_repaint();
+        }
+
+        // サムネイルは描画フレームごとに少しずつ書き出す
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_thumbnails(ctx);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let save = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
+            if ctx.input_mut(|i| i.consume_shortcut(&save)) {
+                self.save_selected_definition();
+            }
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 #[cfg(not(target_arch = "wasm32"))]
@@ -68,6 +120,54 @@ impl eframe::App for MainApp {
                             ui.close_menu();
                         }
 
+                        if ui
+                            .add_enabled(can_save, egui::Button::new("Save"))
+                            .clicked()
+                        {
+                            self.save_selected_definition();
+                            ui.close_menu();
+                        }
+
+                        let has_selection = self.state.selected_definition_index().is_some();
+                        if ui
+                            .add_enabled(has_selection, egui::Button::new("Export OBJ"))
+                            .clicked()
+                        {
+                            self.export_obj(Some(frame));
+                            ui.close_menu();
+                        }
+
+                        if ui
+                            .add_enabled(has_selection, egui::Button::new("Export glTF"))
+                            .clicked()
+                        {
+                            self.export_glb(Some(frame));
+                            ui.close_menu();
+                        }
+
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            if ui
+                                .add_enabled(
+                                    !self.state.definitions().is_empty()
+                                        && self.thumbnail_task.is_none(),
+                                    egui::Button::new("Render Thumbnails"),
+                                )
+                                .clicked()
+                            {
+                                self.render_thumbnails(frame);
+                                ui.close_menu();
+                            }
+                            let mut size = self.state.thumbnail_size();
+                            ui.add(
+                                egui::DragValue::new(&mut size)
+                                    .range(16..=4096)
+                                    .suffix(" px"),
+                            );
+                            self.state.set_thumbnail_size(size);
+                        });
+                        ui.separator();
+
                         if ui.button("Quit").clicked() {
                             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                         }
@@ -76,6 +176,24 @@ impl eframe::App for MainApp {
                     ui.separator();
                 }
 
+                ui.menu_button("Edit", |ui| {
+                    if ui
+                        .add_enabled(can_undo, egui::Button::new("Undo"))
+                        .clicked()
+                    {
+                        self.state.undo();
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(can_redo, egui::Button::new("Redo"))
+                        .clicked()
+                    {
+                        self.state.redo();
+                        ui.close_menu();
+                    }
+                });
+                ui.separator();
+
                 egui::widgets::global_theme_preference_buttons(ui);
             });
         });
@@ -128,6 +246,17 @@ impl eframe::App for MainApp {
 #[cfg(not(target_arch = "wasm32"))]
 use raw_window_handle;
 
+// 書き出し中のサムネイルと、それを描画するレンダラー
+#[cfg(not(target_arch = "wasm32"))]
+struct ThumbnailTask {
+    job: crate::thumbnail::ThumbnailJob,
+    renderer: crate::thumbnail::ThumbnailRenderer,
+}
+
+// 1 フレームでサムネイルの書き出しに使う時間
+#[cfg(not(target_arch = "wasm32"))]
+const THUMBNAIL_FRAME_BUDGET: std::time::Duration = std::time::Duration::from_millis(50);
+
 #[cfg(not(target_arch = "wasm32"))]
 const STORMWORKS_DATA_PATH: &str = "Steam\\steamapps\\common\\Stormworks";
 
@@ -153,4 +282,202 @@ impl MainApp {
             let _ = self.state.open_rom_directory(&pathbuf);
         }
     }
+
+    // ファイルが外で変更または削除されていたら、上書きしてよいか確かめてから保存する
+    fn save_selected_definition(&mut self) {
+        use crate::sw_block_definition::DiskChange;
+        use rfd::{MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+
+        let disk_change = self
+            .state
+            .selected_definition()
+            .and_then(|definition| definition.disk_change());
+        if let Some(change) = disk_change {
+            let description = match change {
+                DiskChange::Modified => {
+                    "The file was changed on disk after it was loaded. Overwrite it with your edits?"
+                }
+                DiskChange::Deleted => {
+                    "The file was deleted on disk after it was loaded. Create it again with your edits?"
+                }
+            };
+            let result = MessageDialog::new()
+                .set_level(MessageLevel::Warning)
+                .set_title("Save")
+                .set_description(description)
+                .set_buttons(MessageButtons::YesNo)
+                .show();
+            if result != MessageDialogResult::Yes {
+                return;
+            }
+        }
+
+        if let Err(err) = self.state.save_selected_definition() {
+            MessageDialog::new()
+                .set_level(MessageLevel::Error)
+                .set_title("Save")
+                .set_description(err.to_string())
+                .show();
+        }
+    }
+
+    fn export_obj<W: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle>(
+        &mut self,
+        parent: Option<&W>,
+    ) {
+        self.export_selected_definition(parent, "Wavefront OBJ", "obj", |definition, path| {
+            let groups = crate::export::definition_export_groups(definition)
+                .map_err(|err| err.to_string())?;
+            crate::export::export_obj(&groups, path).map_err(|err| err.to_string())
+        });
+    }
+
+    fn export_glb<W: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle>(
+        &mut self,
+        parent: Option<&W>,
+    ) {
+        self.export_selected_definition(parent, "glTF Binary", "glb", |definition, path| {
+            let nodes =
+                crate::export::definition_gltf_nodes(definition).map_err(|err| err.to_string())?;
+            crate::export::export_glb(&nodes, path).map_err(|err| err.to_string())
+        });
+    }
+
+    // 選んだフォルダに全ての定義のサムネイルを <ファイル名>.png で書き出す
+    // 書き出しは update_thumbnails で進める
+    fn render_thumbnails(&mut self, frame: &eframe::Frame) {
+        use crate::thumbnail::{ThumbnailJob, ThumbnailRenderer};
+        use rfd::{FileDialog, MessageDialog, MessageLevel};
+
+        let Some(gl) = frame.gl() else {
+            return;
+        };
+        let Some(output_dir) = FileDialog::new().set_parent(frame).pick_folder() else {
+            return;
+        };
+
+        match ThumbnailJob::new(&output_dir) {
+            Ok(job) => {
+                self.thumbnail_task = Some(ThumbnailTask {
+                    job,
+                    renderer: ThumbnailRenderer::new(gl.clone(), self.state.thumbnail_size()),
+                });
+            }
+            Err(err) => {
+                MessageDialog::new()
+                    .set_level(MessageLevel::Error)
+                    .set_title("Render Thumbnails")
+                    .set_description(err.to_string())
+                    .show();
+            }
+        }
+    }
+
+    // 時間の許す分だけ書き出して進み具合を表示する
+    // 中止または完了したら、失敗した定義があれば知らせる
+    fn update_thumbnails(&mut self, ctx: &egui::Context) {
+        use rfd::{MessageDialog, MessageLevel};
+
+        let Some(task) = &mut self.thumbnail_task else {
+            return;
+        };
+        let keep_meshes = *self.state.selected_definition_index();
+        let start = std::time::Instant::now();
+        while !task.job.is_finished(self.state.definitions())
+            && start.elapsed() < THUMBNAIL_FRAME_BUDGET
+        {
+            task.job.render_next(
+                &mut task.renderer,
+                self.state.definitions_mut(),
+                keep_meshes,
+            );
+        }
+
+        let total = self.state.definitions().len();
+        let done = task.job.done_count().min(total);
+        let mut cancel = false;
+        egui::Window::new("Render Thumbnails")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.add(
+                    egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                        .desired_width(300.0)
+                        .text(format!("{}/{}", done, total)),
+                );
+                if !task.job.errors().is_empty() {
+                    ui.weak(format!("{} errors", task.job.errors().len()));
+                }
+                cancel = ui.button("Cancel").clicked();
+            });
+
+        if !cancel && !task.job.is_finished(self.state.definitions()) {
+            ctx.request_repaint();
+            return;
+        }
+
+        let task = self.thumbnail_task.take().unwrap();
+        task.renderer.destroy();
+        let errors = task.job.into_errors();
+        if errors.is_empty() {
+            return;
+        }
+        MessageDialog::new()
+            .set_level(MessageLevel::Warning)
+            .set_title("Render Thumbnails")
+            .set_description(
+                errors
+                    .iter()
+                    .map(|(filename, err)| format!("{}: {}", filename, err))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+            .show();
+    }
+
+    fn export_selected_definition<
+        W: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle,
+    >(
+        &mut self,
+        parent: Option<&W>,
+        filter_name: &str,
+        extension: &str,
+        export: impl FnOnce(
+            &mut crate::sw_block_definition::SwBlockDefinition,
+            &std::path::Path,
+        ) -> Result<(), String>,
+    ) {
+        use rfd::{FileDialog, MessageDialog, MessageLevel};
+
+        let Some(definition) = self.state.selected_definition() else {
+            return;
+        };
+
+        let mut dialog = FileDialog::new()
+            .add_filter(filter_name, &[extension])
+            .set_file_name(format!(
+                "{}.{}",
+                definition
+                    .path()
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy(),
+                extension
+            ));
+        if let Some(p) = parent {
+            dialog = dialog.set_parent(p)
+        }
+        let Some(pathbuf) = dialog.save_file() else {
+            return;
+        };
+
+        if let Err(mes) = export(definition, &pathbuf) {
+            MessageDialog::new()
+                .set_level(MessageLevel::Error)
+                .set_title(format!("Export {}", filter_name))
+                .set_description(mes)
+                .show();
+        }
+    }
 }
@@ -1,16 +1,81 @@
 use super::State;
-use crate::gl_renderer::{Color4, Line, OrbitCamera, Scene, SceneObject, SceneRenderer};
-use crate::sw_block_definition::create_surface_object;
+use crate::gl_renderer::{
+    Camera, Color4, Line, LineVertex, OrbitCamera, PickShape, Ray, Scene, SceneObject,
+    SceneRenderer,
+};
+use crate::sw_block_definition::{
+    coupling_gender_name, coupling_position, create_coupling_objects, create_logic_node_objects,
+    create_surface_mesh, create_surface_object, create_voxel_bounds_object, create_voxel_objects,
+    definition_schema::{Coupling, LogicNode, Surface, Voxel},
+    flag_labels, logic_node_position, surface_shape_warnings, LogicNodeMode, LogicNodeType,
+    SwMeshType,
+};
 use eframe::egui_glow;
 use egui::{mutex::Mutex, vec2};
-use glam::Vec3;
+use glam::{IVec3, Vec3};
 use std::sync::Arc;
 
+const PHYSICS_COLOR: Color4 = Color4 {
+    r: 1.0,
+    g: 0.3,
+    b: 0.8,
+    a: 0.3,
+};
+const PHYSICS_EDGE_COLOR: Color4 = Color4 {
+    r: 1.0,
+    g: 0.3,
+    b: 0.8,
+    a: 1.0,
+};
+
+const BUOYANCY_COLOR: Color4 = Color4 {
+    r: 0.1,
+    g: 0.4,
+    b: 1.0,
+    a: 0.5,
+};
+const PICKED_COLOR: Color4 = Color4 {
+    r: 1.0,
+    g: 1.0,
+    b: 0.0,
+    a: 1.0,
+};
+const VOXEL_BOUNDS_COLOR: Color4 = Color4 {
+    r: 1.0,
+    g: 0.9,
+    b: 0.2,
+    a: 1.0,
+};
+
+// プレビュー用の大まかな塗装色
+// ゲームのパレットから取った値ではなく、見た目で近い色を選んだだけ
+const PAINT_PRESETS: [(&str, [u8; 3]); 12] = [
+    ("White", [255, 255, 255]),
+    ("Light Grey", [170, 170, 170]),
+    ("Dark Grey", [80, 80, 80]),
+    ("Black", [25, 25, 25]),
+    ("Red", [200, 30, 30]),
+    ("Orange", [230, 120, 20]),
+    ("Yellow", [240, 200, 30]),
+    ("Green", [40, 150, 50]),
+    ("Cyan", [40, 180, 200]),
+    ("Blue", [30, 70, 180]),
+    ("Purple", [120, 50, 160]),
+    ("Brown", [110, 70, 40]),
+];
+
 pub struct Definition3dPanel {
     scene: Arc<Mutex<Scene>>,
     camera: Arc<Mutex<OrbitCamera>>,
     renderer: Option<Arc<egui::mutex::Mutex<SceneRenderer>>>,
-    //framebuffer: Option<MultisampleFramebuffer>,
+    // ポインタを近づけたときに表示するラベルとその位置 (ビューアの右手系)
+    hover_labels: Vec<(Vec3, String)>,
+    // クリックで選べるものと選んだものの添字、クリックした位置
+    pick_targets: Vec<PickTarget>,
+    picked: Option<(usize, egui::Pos2)>,
+    // スクリーンショットを描画し直すときに使う
+    #[cfg(not(target_arch = "wasm32"))]
+    gl: Arc<eframe::glow::Context>,
 }
 
 impl Definition3dPanel {
@@ -29,7 +94,11 @@ impl Definition3dPanel {
             scene: scene.clone(),
             camera,
             renderer: Some(Arc::new(egui::mutex::Mutex::new(renderer))),
-            //framebuffer: MultisampleFramebuffer::new(gl.clone(), 512, 512, 16),
+            hover_labels: Vec::new(),
+            pick_targets: Vec::new(),
+            picked: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            gl: gl.clone(),
         })
     }
 
@@ -40,11 +109,10 @@ impl Definition3dPanel {
     }
 
     pub fn ui(&mut self, ui: &mut egui::Ui, state: &mut State) {
-        egui::Frame::canvas(ui.style())
+        let clicked = egui::Frame::canvas(ui.style())
             .fill(egui::Color32::TRANSPARENT)
-            .show(ui, |ui| {
-                self.custom_painting(ui);
-            });
+            .show(ui, |ui| self.custom_painting(ui))
+            .inner;
 
         let mut c = state.show_xyz_axis();
         ui.checkbox(&mut c, "XYZ Axis");
@@ -58,22 +126,57 @@ impl Definition3dPanel {
         ui.checkbox(&mut c, "Surface Edge Lines");
         state.set_show_surface_edge(c);
 
-        if let Some(definition) = state.selected_definition() {
-            let meshes = definition.meshes();
+        let mut c = state.show_buoyancy_surfaces();
+        ui.checkbox(&mut c, "Buoyancy Surfaces");
+        state.set_show_buoyancy_surfaces(c);
+
+        let mut c = state.show_physics_only();
+        ui.checkbox(&mut c, "Physics Only");
+        state.set_show_physics_only(c);
+
+        let mut c = state.show_logic_nodes();
+        ui.checkbox(&mut c, "Logic Nodes");
+        state.set_show_logic_nodes(c);
+
+        let mut c = state.show_voxels();
+        ui.checkbox(&mut c, "Voxels");
+        state.set_show_voxels(c);
+
+        let mut c = state.show_couplings();
+        ui.checkbox(&mut c, "Couplings");
+        state.set_show_couplings(c);
 
+        if state.is_selected_definition_loading() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak("Loading");
+            });
+        }
+
+        // メッシュはワーカースレッドで読み込み終わってから表示する
+        if let Some(meshes) = state
+            .loaded_selected_definition()
+            .and_then(|definition| definition.loaded_meshes())
+        {
             let mut change = None;
             for (key, show) in state.show_mesh() {
                 if let Some(mesh) = meshes.get_mesh(&key) {
                     let name = key.xml_name();
-                    if let Err(err) = mesh {
-                        ui.collapsing(format!("{}: Error", name), |ui| {
-                            ui.label(format!("{}", err));
-                        });
-                    } else {
-                        let mut c = *show;
-                        ui.checkbox(&mut c, name);
-                        if c != *show {
-                            change = Some((key, c));
+                    match mesh {
+                        Err(err) => {
+                            ui.collapsing(format!("{}: Error", name), |ui| {
+                                ui.label(format!("{}", err));
+                            });
+                        }
+                        Ok(mesh) => {
+                            let mut c = *show;
+                            match mesh.mesh_type() {
+                                SwMeshType::Mesh => ui.checkbox(&mut c, name),
+                                SwMeshType::Phys => ui.checkbox(&mut c, format!("{} (phys)", name)),
+                            };
+                            if c != *show {
+                                change = Some((key, c));
+                            }
                         }
                     }
                 }
@@ -83,18 +186,67 @@ impl Definition3dPanel {
             }
         }
 
+        if state.show_couplings() {
+            self.coupling_list(ui, state);
+        }
+
+        paint_colors_ui(ui, state);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.screenshot_ui(ui, state);
+
+        if let Some(data) = state
+            .loaded_selected_definition()
+            .and_then(|def| def.data().ok())
+        {
+            let warnings = surface_shape_warnings(&data);
+            if !warnings.is_empty() {
+                ui.collapsing(format!("Warnings ({})", warnings.len()), |ui| {
+                    for warning in warnings {
+                        ui.colored_label(ui.visuals().warn_fg_color, warning);
+                    }
+                });
+            }
+        }
+
         if state.is_changed() {
+            self.picked = None;
+            self.update_scene(state);
+        } else if let Some((rect, pointer)) = clicked {
+            self.picked = self.pick(rect, pointer).map(|i| (i, pointer));
             self.update_scene(state);
         }
     }
 
-    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+    // クリックされたら描画範囲とクリック位置を返す
+    fn custom_painting(&mut self, ui: &mut egui::Ui) -> Option<(egui::Rect, egui::Pos2)> {
         let size = ui.available_width();
-        let (rect, response) = ui.allocate_exact_size(vec2(size, size), egui::Sense::drag());
+        let (rect, response) =
+            ui.allocate_exact_size(vec2(size, size), egui::Sense::click_and_drag());
 
-        self.camera.lock().control(ui, response);
+        self.camera.lock().control(ui, response.clone());
         let camera = self.camera.clone();
 
+        let clicked = response
+            .clicked()
+            .then(|| response.interact_pointer_pos())
+            .flatten()
+            .map(|pointer| (rect, pointer));
+
+        if let Some((i, pointer)) = self.picked {
+            egui::show_tooltip_at(
+                ui.ctx(),
+                ui.layer_id(),
+                response.id.with("picked"),
+                pointer + vec2(12.0, 12.0),
+                |ui| ui.label(&self.pick_targets[i].label),
+            );
+        } else if let Some(pointer) = response.hover_pos() {
+            if let Some(label) = self.hover_label(rect, pointer) {
+                response.on_hover_text_at_pointer(label);
+            }
+        }
+
         if let Some(renderer) = self.renderer.clone() {
             let cb = egui_glow::CallbackFn::new(move |_info, painter| {
                 renderer.lock().paint(painter.gl(), camera.clone());
@@ -106,10 +258,192 @@ impl Definition3dPanel {
             };
             ui.painter().add(callback);
         }
+
+        clicked
+    }
+
+    // クリック位置から視線を飛ばして最も手前で当たったものを返す
+    // ボクセルは他に何も当たらなかったときだけ選ぶ
+    fn pick(&self, rect: egui::Rect, pointer: egui::Pos2) -> Option<usize> {
+        let ndc = glam::Vec2::new(
+            (pointer.x - rect.left()) / rect.width() * 2.0 - 1.0,
+            1.0 - (pointer.y - rect.top()) / rect.height() * 2.0,
+        );
+        let ray = Ray::from_camera(&*self.camera.lock(), ndc);
+        let nearest = |fallback: bool| {
+            self.pick_targets
+                .iter()
+                .enumerate()
+                .filter(|(_, target)| target.fallback == fallback)
+                .filter_map(|(i, target)| ray.intersect(&target.shape).map(|t| (t, i)))
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(_, i)| i)
+        };
+        nearest(false).or_else(|| nearest(true))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn screenshot_ui(&mut self, ui: &mut egui::Ui, state: &mut State) {
+        ui.collapsing("Screenshot", |ui| {
+            ui.horizontal(|ui| {
+                let mut width = state.screenshot_width();
+                let mut height = state.screenshot_height();
+                // GPU が作れるフレームバッファより大きくしない
+                let max_size = crate::gl_renderer::MultisampleFramebuffer::max_size(&self.gl)
+                    .clamp(16, 8192) as u32;
+                ui.add(egui::DragValue::new(&mut width).range(16..=max_size));
+                ui.label("×");
+                ui.add(egui::DragValue::new(&mut height).range(16..=max_size));
+                state.set_screenshot_width(width);
+                state.set_screenshot_height(height);
+            });
+
+            let mut c = state.screenshot_transparent();
+            ui.checkbox(&mut c, "Transparent Background");
+            state.set_screenshot_transparent(c);
+
+            if ui.button("Save Screenshot").clicked() {
+                let background = if state.screenshot_transparent() {
+                    Color4::new(0.0, 0.0, 0.0, 0.0)
+                } else {
+                    let [r, g, b, a] = ui.visuals().panel_fill.to_normalized_gamma_f32();
+                    Color4::new(r, g, b, a)
+                };
+                self.save_screenshot(
+                    state.screenshot_width(),
+                    state.screenshot_height(),
+                    background,
+                );
+            }
+        });
+    }
+
+    // 今のカメラのままシーンを width × height で描画し直して PNG に保存する
+    // 縦の視野角はそのままで、横は縦横比に合わせて広がる
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_screenshot(&self, width: u32, height: u32, background: Color4) {
+        use crate::gl_renderer::MultisampleFramebuffer;
+        use eframe::glow::{self, HasContext as _};
+        use rfd::{FileDialog, MessageDialog, MessageLevel};
+
+        let show_error = |message: String| {
+            MessageDialog::new()
+                .set_level(MessageLevel::Error)
+                .set_title("Save Screenshot")
+                .set_description(message)
+                .show();
+        };
+
+        let Some(renderer) = &self.renderer else {
+            return;
+        };
+        let Some(path) = FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .set_file_name("screenshot.png")
+            .save_file()
+        else {
+            return;
+        };
+
+        let max_size = MultisampleFramebuffer::max_size(&self.gl).max(1) as u32;
+        let (width, height) = (width.min(max_size), height.min(max_size));
+        let mut camera = self.camera.lock().clone();
+        camera.aspect_ratio = width as f32 / height as f32;
+        let framebuffer =
+            match MultisampleFramebuffer::new(self.gl.clone(), width as i32, height as i32, 16) {
+                Ok(framebuffer) => framebuffer,
+                Err(err) => return show_error(err),
+            };
+        let image = renderer.lock().paint_offscreen(
+            &self.gl,
+            Arc::new(Mutex::new(camera)),
+            &framebuffer,
+            background,
+        );
+
+        // 描画に失敗した画像は保存しない
+        let error = unsafe { self.gl.get_error() };
+        if error != glow::NO_ERROR {
+            return show_error(format!(
+                "Failed to render screenshot: GL error 0x{:X}",
+                error
+            ));
+        }
+        if let Err(err) = image.save(&path) {
+            show_error(err.to_string());
+        }
+    }
+
+    // 選んだカプラーを 3D ビューで強調表示する
+    fn coupling_list(&mut self, ui: &mut egui::Ui, state: &mut State) {
+        let Some(data) = state
+            .loaded_selected_definition()
+            .and_then(|def| def.data().ok())
+        else {
+            return;
+        };
+        let couplings: Vec<&Coupling> = data
+            .couplings
+            .iter()
+            .flat_map(|couplings| &couplings.coupling)
+            .collect();
+        if couplings.is_empty() {
+            return;
+        }
+
+        let selected = state.selected_coupling_index();
+        let mut set_index = None;
+        ui.collapsing(format!("Couplings ({})", couplings.len()), |ui| {
+            for (i, coupling) in couplings.iter().enumerate() {
+                if ui
+                    .selectable_label(Some(i) == selected, coupling_label(coupling))
+                    .clicked()
+                {
+                    set_index = Some(if Some(i) == selected { None } else { Some(i) });
+                }
+            }
+        });
+        if let Some(value) = set_index {
+            state.set_selected_coupling_index(value);
+        }
+    }
+
+    // 画面上でポインタに最も近いラベルを返す
+    fn hover_label(&self, rect: egui::Rect, pointer: egui::Pos2) -> Option<&str> {
+        const HOVER_DISTANCE: f32 = 10.0;
+
+        let mat_view_proj = self.camera.lock().mat_view_proj();
+        self.hover_labels
+            .iter()
+            .filter_map(|(position, label)| {
+                let clip = mat_view_proj * position.extend(1.0);
+                if clip.w <= 0.0 {
+                    return None;
+                }
+                let ndc = clip.truncate() / clip.w;
+                let screen = egui::pos2(
+                    rect.left() + (ndc.x + 1.0) / 2.0 * rect.width(),
+                    rect.top() + (1.0 - ndc.y) / 2.0 * rect.height(),
+                );
+                let distance = screen.distance(pointer);
+                (distance < HOVER_DISTANCE).then_some((distance, label.as_str()))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, label)| label)
     }
 
     fn update_scene(&mut self, state: &mut State) {
+        if let Some(renderer) = &self.renderer {
+            renderer
+                .lock()
+                .set_override_colors(state.paint_colors().map(|[r, g, b]| {
+                    Color4::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0)
+                }));
+        }
+
         self.scene.lock().clear();
+        self.hover_labels.clear();
+        self.pick_targets.clear();
 
         if state.show_xyz_axis() {
             for (direction, color) in [
@@ -124,11 +458,22 @@ impl Definition3dPanel {
             }
         }
 
-        if let Some(data) = state.selected_definition().and_then(|def| def.data().ok()) {
+        let physics_only = state.show_physics_only();
+        let show_voxels = state.show_voxels();
+
+        if let Some(data) = state
+            .loaded_selected_definition()
+            .and_then(|def| def.data().ok())
+            .filter(|_| !physics_only)
+        {
             if let Some(surfaces) = data.surfaces.last() {
-                for surface in &surfaces.surface {
+                for (i, surface) in surfaces.surface.iter().enumerate() {
+                    if state.show_surfaces() {
+                        self.add_surface_target("surface", i, surface);
+                    }
                     let (mesh_obj, line_obj) = create_surface_object(
                         surface,
+                        Color4::WHITE,
                         state.show_surfaces(),
                         state.show_surface_edge(),
                     );
@@ -140,23 +485,282 @@ impl Definition3dPanel {
                     }
                 }
             }
+
+            if state.show_buoyancy_surfaces() {
+                if let Some(surfaces) = data.buoyancy_surfaces.last() {
+                    for (i, surface) in surfaces.surface.iter().enumerate() {
+                        self.add_surface_target("buoyancy surface", i, surface);
+                        let (mesh_obj, line_obj) = create_surface_object(
+                            surface,
+                            BUOYANCY_COLOR,
+                            true,
+                            state.show_surface_edge(),
+                        );
+                        if let Some(obj) = mesh_obj {
+                            self.scene.lock().add_object(obj);
+                        }
+                        if let Some(obj) = line_obj {
+                            self.scene.lock().add_object(obj);
+                        }
+                    }
+                }
+            }
+
+            if state.show_logic_nodes() {
+                for logic_nodes in &data.logic_nodes {
+                    for node in &logic_nodes.logic_node {
+                        for obj in create_logic_node_objects(node) {
+                            self.scene.lock().add_object(obj);
+                        }
+                        let position = logic_node_position(node);
+                        self.hover_labels.push((position, logic_node_label(node)));
+                        self.pick_targets.push(PickTarget {
+                            shape: PickShape::cube(position, 0.04),
+                            label: format!("logic node\n{}", logic_node_label(node)),
+                            fallback: false,
+                        });
+                    }
+                }
+            }
+
+            if state.show_couplings() {
+                let selected = state.selected_coupling_index();
+                let couplings = data
+                    .couplings
+                    .iter()
+                    .flat_map(|couplings| &couplings.coupling);
+                for (i, coupling) in couplings.enumerate() {
+                    for obj in create_coupling_objects(coupling, Some(i) == selected) {
+                        self.scene.lock().add_object(obj);
+                    }
+                    let position = coupling_position(coupling);
+                    self.hover_labels.push((position, coupling_label(coupling)));
+                    self.pick_targets.push(PickTarget {
+                        shape: PickShape::cube(position, 0.06),
+                        label: format!("coupling #{}\n{}", i, coupling_label(coupling)),
+                        fallback: false,
+                    });
+                }
+            }
         }
 
-        if let Some(definition) = state.selected_definition() {
-            let meshes = definition.meshes();
+        // ボクセルは物理形状なので Physics Only でも表示する
+        if let Some(data) = state
+            .loaded_selected_definition()
+            .and_then(|def| def.data().ok())
+            .filter(|_| show_voxels)
+        {
+            let voxels: Vec<&Voxel> = data
+                .voxels
+                .iter()
+                .flat_map(|voxels| &voxels.voxel)
+                .collect();
+            for obj in create_voxel_objects(&voxels) {
+                self.scene.lock().add_object(obj);
+            }
+            for voxel in &voxels {
+                let p = voxel.position.last().cloned().unwrap_or_default();
+                self.pick_targets.push(PickTarget {
+                    shape: PickShape::cube(
+                        0.25 * Vec3::new(p.x as f32, p.y as f32, -p.z as f32),
+                        0.125,
+                    ),
+                    label: format!(
+                        "voxel ({}, {}, {})\nphysics_shape {}, flags {} [{}], buoy_pipes {}",
+                        p.x,
+                        p.y,
+                        p.z,
+                        voxel.physics_shape.unwrap_or(0),
+                        voxel.flags.unwrap_or(0),
+                        flag_labels(voxel.flags.unwrap_or(0)),
+                        voxel.buoy_pipes.unwrap_or(0)
+                    ),
+                    fallback: true,
+                });
+            }
+
+            let bounds = [
+                (
+                    data.voxel_min.last().map(|v| IVec3::new(v.x, v.y, v.z)),
+                    data.voxel_max.last().map(|v| IVec3::new(v.x, v.y, v.z)),
+                    VOXEL_BOUNDS_COLOR,
+                ),
+                (
+                    data.voxel_physics_min
+                        .last()
+                        .map(|v| IVec3::new(v.x, v.y, v.z)),
+                    data.voxel_physics_max
+                        .last()
+                        .map(|v| IVec3::new(v.x, v.y, v.z)),
+                    PHYSICS_EDGE_COLOR,
+                ),
+            ];
+            for (min, max, color) in bounds {
+                if let (Some(min), Some(max)) = (min, max) {
+                    self.scene
+                        .lock()
+                        .add_object(create_voxel_bounds_object(min, max, color));
+                }
+            }
+        }
 
+        if let Some(meshes) = state
+            .loaded_selected_definition()
+            .and_then(|definition| definition.loaded_meshes())
+        {
             for (key, show) in state.show_mesh() {
                 if !*show {
                     continue;
                 }
                 if let Some(Ok(mesh)) = meshes.get_mesh(&key) {
-                    for m in mesh.as_meshes() {
-                        self.scene
-                            .lock()
-                            .add_object(SceneObject::from_mesh(m, None));
+                    if !physics_only || mesh.mesh_type() == SwMeshType::Phys {
+                        for (i, (submesh, m)) in
+                            mesh.submeshes().iter().zip(mesh.as_meshes()).enumerate()
+                        {
+                            self.pick_targets.push(PickTarget {
+                                shape: PickShape::from_mesh(&m),
+                                label: format!(
+                                    "{}\nsubmesh #{} {}\nshader_id {}",
+                                    key.xml_name(),
+                                    i,
+                                    submesh.name().unwrap_or_default(),
+                                    submesh.shader_id()
+                                ),
+                                fallback: false,
+                            });
+                        }
+                    }
+                }
+                match meshes.get_mesh(&key) {
+                    Some(Ok(mesh)) if mesh.mesh_type() == SwMeshType::Phys => {
+                        // 物理メッシュは見た目のメッシュに重ねて半透明とワイヤーフレームで表示する
+                        let mut scene = self.scene.lock();
+                        scene.add_object(SceneObject::from_mesh(
+                            mesh.as_physics_mesh(PHYSICS_COLOR),
+                            None,
+                        ));
+                        scene.add_object(SceneObject::from_line(
+                            mesh.as_wireframe(PHYSICS_EDGE_COLOR, 1.0),
+                            None,
+                        ));
+                    }
+                    Some(Ok(mesh)) if !physics_only => {
+                        for m in mesh.as_meshes() {
+                            self.scene
+                                .lock()
+                                .add_object(SceneObject::from_mesh(m, None));
+                        }
                     }
+                    _ => {}
                 }
             }
         }
+
+        if let Some((i, _)) = self.picked {
+            let vertices = self.pick_targets[i]
+                .shape
+                .edges()
+                .into_iter()
+                .flat_map(|(a, b)| {
+                    [a, b].map(|position| LineVertex {
+                        position,
+                        color: PICKED_COLOR,
+                    })
+                })
+                .collect();
+            self.scene
+                .lock()
+                .add_object(SceneObject::from_line(Line::new(vertices, 2.0), None));
+        }
+    }
+
+    fn add_surface_target(&mut self, layer: &str, index: usize, surface: &Surface) {
+        if let Some(mesh) = create_surface_mesh(surface) {
+            self.pick_targets.push(PickTarget {
+                shape: PickShape::from_mesh(&mesh),
+                label: format!(
+                    "{} #{}\nshape {}, orientation {}, rotation {}",
+                    layer,
+                    index,
+                    surface.shape.unwrap_or(0),
+                    surface.orientation.unwrap_or(0),
+                    surface.rotation.unwrap_or(0)
+                ),
+                fallback: false,
+            });
+        }
+    }
+}
+
+struct PickTarget {
+    shape: PickShape,
+    label: String,
+    // 他に何も当たらなかったときだけ選ぶ
+    fallback: bool,
+}
+
+// ペイントスロットごとに色を選ぶ。大まかなプリセットから選ぶこともできる
+fn paint_colors_ui(ui: &mut egui::Ui, state: &mut State) {
+    ui.collapsing("Paint Colors", |ui| {
+        let mut colors = state.paint_colors();
+        for (i, color) in colors.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgb(color);
+                ui.menu_button(format!("Color {}", i + 1), |ui| {
+                    ui.weak("Approximate colors");
+                    for (name, preset) in PAINT_PRESETS {
+                        let [r, g, b] = preset;
+                        let clicked = ui
+                            .horizontal(|ui| {
+                                ui.colored_label(egui::Color32::from_rgb(r, g, b), "■");
+                                ui.button(name).clicked()
+                            })
+                            .inner;
+                        if clicked {
+                            *color = preset;
+                            ui.close_menu();
+                        }
+                    }
+                });
+            });
+        }
+        if ui.button("Reset").clicked() {
+            colors = [[255; 3]; 3];
+        }
+        state.set_paint_colors(colors);
+    });
+}
+
+fn logic_node_label(node: &LogicNode) -> String {
+    let mut label = format!(
+        "{}\n{} {}",
+        node.label.as_deref().unwrap_or_default(),
+        LogicNodeType::from_i32(node.node_type.unwrap_or(0)).label(),
+        LogicNodeMode::from_i32(node.mode.unwrap_or(0)).label(),
+    );
+    if let Some(description) = node.description.as_deref() {
+        if !description.is_empty() {
+            label = format!("{}\n{}", label, description);
+        }
+    }
+    let flags = node.flags.unwrap_or(0);
+    if flags != 0 {
+        label = format!("{}\nflags [{}]", label, flag_labels(flags));
+    }
+    label
+}
+
+fn coupling_label(coupling: &Coupling) -> String {
+    let mut label = format!(
+        "{} ({})",
+        coupling.coupling_type.as_deref().unwrap_or_default(),
+        coupling_gender_name(coupling.coupling_gender.unwrap_or(-1)),
+    );
+    if coupling.alignment_required.unwrap_or(false) {
+        label += ", alignment required";
+    }
+    if coupling.allow_bipolar_alignment.unwrap_or(false) {
+        label += ", bipolar";
     }
+    label
 }
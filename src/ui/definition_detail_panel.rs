@@ -1,16 +1,36 @@
 use super::State;
-use std::fmt::Debug;
+use crate::sw_block_definition::{
+    attribute_value_label, attribute_value_names,
+    definition_schema::{AttributeValue, OwnedAttributeValue, Surface},
+    flag_labels, is_known_surface_shape, XmlElement,
+};
 
 #[derive(Default)]
-pub struct DefinitionDetailPanel {}
+pub struct DefinitionDetailPanel {
+    // 入力中のテキスト欄の (定義の添字, 属性名, 文字列)
+    // フォーカスが外れたときに値を確定する。別の定義を選んだら使わない
+    editing: Option<(usize, String, String)>,
+}
 
 impl DefinitionDetailPanel {
     pub fn ui(&mut self, ui: &mut egui::Ui, state: &mut State) {
+        if state.is_selected_definition_loading() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Loading");
+            });
+            return;
+        }
+
         let definition = state.selected_definition();
         if definition.is_none() {
             return;
         }
-        let data = definition.unwrap().data();
+        let definition = definition.unwrap();
+        let is_dirty = definition.is_dirty();
+        #[cfg(not(target_arch = "wasm32"))]
+        let disk_change = definition.disk_change();
+        let data = definition.data();
         if let Err(err) = data {
             ui.collapsing("Error", |ui| {
                 ui.label(err.to_string());
@@ -20,286 +40,338 @@ impl DefinitionDetailPanel {
         let data = data.unwrap();
 
         if let Some(name) = &data.name {
-            ui.heading(name);
+            if is_dirty {
+                ui.heading(format!("{} *", name));
+            } else {
+                ui.heading(name);
+            }
+        }
+
+        // 編集中にファイルが外で変更されたら、編集を捨てて読み込み直すか選ばせる
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(change) = disk_change {
+            use crate::sw_block_definition::DiskChange;
+
+            ui.horizontal(|ui| {
+                let (message, button) = match change {
+                    DiskChange::Modified => {
+                        ("The file was changed on disk.", "Discard edits and reload")
+                    }
+                    DiskChange::Deleted => {
+                        ("The file was deleted on disk.", "Discard edits and remove")
+                    }
+                };
+                ui.colored_label(ui.visuals().warn_fg_color, message);
+                if ui.button(button).clicked() {
+                    state.reload_selected_definition();
+                }
+            });
+            if !state.selected_definition().is_some_and(|d| d.is_loaded()) {
+                return;
+            }
+        }
+
+        let show_all = state.show_all_attributes();
+        let hide_default = state.hide_default_attributes();
+        let index = state.selected_definition_index().unwrap_or_default();
+        let mut edit = None;
+
+        egui::Grid::new("definition_detail_table")
+            .num_columns(2)
+            .spacing([10.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                for (name, value) in data.attributes() {
+                    if (show_all || value.is_some()) && !(hide_default && is_default(value)) {
+                        ui.label(name);
+                        if let Some(new_value) = self.attribute_editor(ui, index, name, value) {
+                            edit = Some((name, new_value));
+                        }
+                        ui.end_row();
+                    }
+                }
+            });
+
+        let surface_layers = [
+            ("Surfaces", data.surfaces.last().map(|s| &s.surface)),
+            (
+                "Buoyancy Surfaces",
+                data.buoyancy_surfaces.last().map(|s| &s.surface),
+            ),
+        ];
+        for (title, surfaces) in surface_layers {
+            if let Some(surfaces) = surfaces.filter(|s| !s.is_empty()) {
+                ui.add_space(8.0);
+                ui.collapsing(format!("{} ({})", title, surfaces.len()), |ui| {
+                    for (i, surface) in surfaces.iter().enumerate() {
+                        surface_inspector(ui, title, i, surface);
+                    }
+                });
+            }
+        }
+
+        // スキーマに無い属性と要素は編集できないがそのまま保存される
+        if !data.extra.is_empty() {
+            ui.add_space(8.0);
+            ui.collapsing("Unrecognized", |ui| {
+                egui::Grid::new("definition_unrecognized_table")
+                    .num_columns(2)
+                    .spacing([10.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (name, value) in &data.extra.attributes {
+                            ui.label(name);
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                    });
+                for element in &data.extra.elements {
+                    element_tree(ui, element);
+                }
+            });
         }
 
-        attribute_table_body(
-            ui,
-            "definition_detail_table",
-            state.show_all_attributes(),
-            state.hide_default_attributes(),
-            [
-                ("name", fmt_default(&data.name)),
-                ("category", fmt_default(&data.category)),
-                ("type", fmt_default(&data.definition_type)),
-                ("mass", fmt_default(&data.mass)),
-                ("value", fmt_default(&data.value)),
-                ("flags", fmt_default(&data.flags)),
-                ("tags", fmt_default(&data.tags)),
-                (
-                    "phys_collision_dampen",
-                    fmt_default(&data.phys_collision_dampen),
-                ),
-                (
-                    "audio_filename_start",
-                    fmt_default(&data.audio_filename_start),
-                ),
-                (
-                    "audio_filename_loop",
-                    fmt_default(&data.audio_filename_loop),
-                ),
-                ("audio_filename_end", fmt_default(&data.audio_filename_end)),
-                (
-                    "audio_filename_start_b",
-                    fmt_default(&data.audio_filename_start_b),
-                ),
-                (
-                    "audio_filename_loop_b",
-                    fmt_default(&data.audio_filename_loop_b),
-                ),
-                (
-                    "audio_filename_end_b",
-                    fmt_default(&data.audio_filename_end_b),
-                ),
-                ("audio_gain", fmt_default(&data.audio_gain)),
-                ("mesh_data_name", fmt_default(&data.mesh_data_name)),
-                ("mesh_0_name", fmt_default(&data.mesh_0_name)),
-                ("mesh_1_name", fmt_default(&data.mesh_1_name)),
-                ("mesh_2_name", fmt_default(&data.mesh_2_name)),
-                (
-                    "mesh_editor_only_name",
-                    fmt_default(&data.mesh_editor_only_name),
-                ),
-                ("block_type", fmt_default(&data.block_type)),
-                ("child_name", fmt_default(&data.child_name)),
-                ("extender_name", fmt_default(&data.extender_name)),
-                ("constraint_type", fmt_default(&data.constraint_type)),
-                ("constraint_axis", fmt_default(&data.constraint_axis)),
-                (
-                    "constraint_range_of_motion",
-                    fmt_default(&data.constraint_range_of_motion),
-                ),
-                ("max_motor_force", fmt_default(&data.max_motor_force)),
-                ("max_motor_speed", fmt_default(&data.max_motor_speed)),
-                ("cable_radius", fmt_default(&data.cable_radius)),
-                ("cable_length", fmt_default(&data.cable_length)),
-                ("seat_type", fmt_default(&data.seat_type)),
-                ("seat_pose", fmt_default(&data.seat_pose)),
-                (
-                    "seat_health_per_sec",
-                    fmt_default(&data.seat_health_per_sec),
-                ),
-                ("buoy_radius", fmt_default(&data.buoy_radius)),
-                ("buoy_factor", fmt_default(&data.buoy_factor)),
-                ("buoy_force", fmt_default(&data.buoy_force)),
-                (
-                    "force_emitter_max_force",
-                    fmt_default(&data.force_emitter_max_force),
-                ),
-                (
-                    "force_emitter_max_vector",
-                    fmt_default(&data.force_emitter_max_vector),
-                ),
-                (
-                    "force_emitter_default_pitch",
-                    fmt_default(&data.force_emitter_default_pitch),
-                ),
-                (
-                    "force_emitter_blade_height",
-                    fmt_default(&data.force_emitter_blade_height),
-                ),
-                (
-                    "force_emitter_rotation_speed",
-                    fmt_default(&data.force_emitter_rotation_speed),
-                ),
-                (
-                    "force_emitter_blade_physics_length",
-                    fmt_default(&data.force_emitter_blade_physics_length),
-                ),
-                (
-                    "force_emitter_blade_efficiency",
-                    fmt_default(&data.force_emitter_blade_efficiency),
-                ),
-                (
-                    "force_emitter_efficiency",
-                    fmt_default(&data.force_emitter_efficiency),
-                ),
-                ("engine_max_force", fmt_default(&data.engine_max_force)),
-                (
-                    "engine_frictionless_force",
-                    fmt_default(&data.engine_frictionless_force),
-                ),
-                ("trans_conn_type", fmt_default(&data.trans_conn_type)),
-                ("trans_type", fmt_default(&data.trans_type)),
-                ("wheel_radius", fmt_default(&data.wheel_radius)),
-                ("wheel_width", fmt_default(&data.wheel_width)),
-                (
-                    "wheel_wishbone_length",
-                    fmt_default(&data.wheel_wishbone_length),
-                ),
-                (
-                    "wheel_suspension_height",
-                    fmt_default(&data.wheel_suspension_height),
-                ),
-                (
-                    "wheel_wishbone_margin",
-                    fmt_default(&data.wheel_wishbone_margin),
-                ),
-                (
-                    "wheel_suspension_offset",
-                    fmt_default(&data.wheel_suspension_offset),
-                ),
-                (
-                    "wheel_wishbone_offset",
-                    fmt_default(&data.wheel_wishbone_offset),
-                ),
-                ("wheel_type", fmt_default(&data.wheel_type)),
-                ("button_type", fmt_default(&data.button_type)),
-                ("light_intensity", fmt_default(&data.light_intensity)),
-                ("light_range", fmt_default(&data.light_range)),
-                ("light_ies_map", fmt_default(&data.light_ies_map)),
-                ("light_fov", fmt_default(&data.light_fov)),
-                ("light_type", fmt_default(&data.light_type)),
-                ("door_lower_limit", fmt_default(&data.door_lower_limit)),
-                ("door_upper_limit", fmt_default(&data.door_upper_limit)),
-                ("door_flipped", fmt_default(&data.door_flipped)),
-                ("custom_door_type", fmt_default(&data.custom_door_type)),
-                ("door_side_dist", fmt_default(&data.door_side_dist)),
-                ("door_up_dist", fmt_default(&data.door_up_dist)),
-                (
-                    "dynamic_min_rotation",
-                    fmt_default(&data.dynamic_min_rotation),
-                ),
-                (
-                    "dynamic_max_rotation",
-                    fmt_default(&data.dynamic_max_rotation),
-                ),
-                ("logic_gate_type", fmt_default(&data.logic_gate_type)),
-                ("logic_gate_subtype", fmt_default(&data.logic_gate_subtype)),
-                ("indicator_type", fmt_default(&data.indicator_type)),
-                ("connector_type", fmt_default(&data.connector_type)),
-                ("magnet_force", fmt_default(&data.magnet_force)),
-                ("gyro_type", fmt_default(&data.gyro_type)),
-                ("reward_tier", fmt_default(&data.reward_tier)),
-                ("revision", fmt_default(&data.revision)),
-                (
-                    "rudder_surface_area",
-                    fmt_default(&data.rudder_surface_area),
-                ),
-                ("pump_pressure", fmt_default(&data.pump_pressure)),
-                ("m_pump_pressure", fmt_default(&data.m_pump_pressure)),
-                (
-                    "water_component_type",
-                    fmt_default(&data.water_component_type),
-                ),
-                (
-                    "torque_component_type",
-                    fmt_default(&data.torque_component_type),
-                ),
-                (
-                    "jet_engine_component_type",
-                    fmt_default(&data.jet_engine_component_type),
-                ),
-                ("particle_speed", fmt_default(&data.particle_speed)),
-                ("inventory_type", fmt_default(&data.inventory_type)),
-                (
-                    "inventory_default_outfit",
-                    fmt_default(&data.inventory_default_outfit),
-                ),
-                ("inventory_class", fmt_default(&data.inventory_class)),
-                (
-                    "inventory_default_item",
-                    fmt_default(&data.inventory_default_item),
-                ),
-                ("electric_type", fmt_default(&data.electric_type)),
-                (
-                    "electric_charge_capacity",
-                    fmt_default(&data.electric_charge_capacity),
-                ),
-                ("electric_magnitude", fmt_default(&data.electric_magnitude)),
-                ("composite_type", fmt_default(&data.composite_type)),
-                ("camera_fov_min", fmt_default(&data.camera_fov_min)),
-                ("camera_fov_max", fmt_default(&data.camera_fov_max)),
-                ("monitor_border", fmt_default(&data.monitor_border)),
-                ("monitor_inset", fmt_default(&data.monitor_inset)),
-                ("weapon_type", fmt_default(&data.weapon_type)),
-                ("weapon_class", fmt_default(&data.weapon_class)),
-                ("weapon_belt_type", fmt_default(&data.weapon_belt_type)),
-                (
-                    "weapon_ammo_capacity",
-                    fmt_default(&data.weapon_ammo_capacity),
-                ),
-                ("weapon_ammo_feed", fmt_default(&data.weapon_ammo_feed)),
-                (
-                    "weapon_barrel_length_voxels",
-                    fmt_default(&data.weapon_barrel_length_voxels),
-                ),
-                ("rx_range", fmt_default(&data.rx_range)),
-                ("rx_length", fmt_default(&data.rx_length)),
-                ("rocket_type", fmt_default(&data.rocket_type)),
-                ("radar_range", fmt_default(&data.radar_range)),
-                ("radar_speed", fmt_default(&data.radar_speed)),
-                ("engine_module_type", fmt_default(&data.engine_module_type)),
-                (
-                    "steam_component_type",
-                    fmt_default(&data.steam_component_type),
-                ),
-                (
-                    "steam_component_capacity",
-                    fmt_default(&data.steam_component_capacity),
-                ),
-                (
-                    "nuclear_component_type",
-                    fmt_default(&data.nuclear_component_type),
-                ),
-                ("radar_type", fmt_default(&data.radar_type)),
-                ("piston_len", fmt_default(&data.piston_len)),
-                ("piston_cam", fmt_default(&data.piston_cam)),
-                (
-                    "data_logger_component_type",
-                    fmt_default(&data.data_logger_component_type),
-                ),
-                (
-                    "metadata_component_type",
-                    fmt_default(&data.metadata_component_type),
-                ),
-                ("oil_component_type", fmt_default(&data.oil_component_type)),
-                ("tool_type", fmt_default(&data.tool_type)),
-            ],
-        );
+        if let Some((name, value)) = edit {
+            state.set_selected_attribute(name, value);
+        }
     }
-}
 
-fn fmt_default<T: Debug + Default + PartialEq>(value: &Option<T>) -> (Option<String>, bool) {
-    if let Some(val) = value {
-        (Some(format!("{:?}", val)), *val == T::default())
-    } else {
-        (None, false)
+    fn attribute_editor(
+        &mut self,
+        ui: &mut egui::Ui,
+        index: usize,
+        name: &str,
+        value: AttributeValue<'_>,
+    ) -> Option<OwnedAttributeValue> {
+        ui.horizontal(|ui| {
+            if !value.is_some() {
+                ui.weak("Not defined");
+                let add = ui
+                    .small_button("+")
+                    .on_hover_text("Add attribute")
+                    .clicked();
+                return add.then(|| default_value(value));
+            }
+
+            let new_value = match value {
+                AttributeValue::Bool(Some(v)) => {
+                    let mut c = *v;
+                    ui.checkbox(&mut c, "")
+                        .changed()
+                        .then_some(OwnedAttributeValue::Bool(Some(c)))
+                }
+                AttributeValue::I32(Some(v)) if attribute_value_names(name).is_some() => {
+                    let text_value = self.text_editor(ui, index, name, value);
+                    let named_value = named_value_menu(ui, name, *v);
+                    text_value.or(named_value)
+                }
+                AttributeValue::U64(Some(v)) => {
+                    let text_value = self.text_editor(ui, index, name, value);
+                    let flags_value = flags_menu(ui, *v);
+                    ui.weak(flag_labels(*v));
+                    text_value.or(flags_value)
+                }
+                _ => self.text_editor(ui, index, name, value),
+            };
+
+            let remove = ui
+                .small_button("×")
+                .on_hover_text("Remove attribute")
+                .clicked();
+            if remove {
+                Some(none_value(value))
+            } else {
+                new_value
+            }
+        })
+        .inner
+    }
+
+    fn text_editor(
+        &mut self,
+        ui: &mut egui::Ui,
+        index: usize,
+        name: &str,
+        value: AttributeValue<'_>,
+    ) -> Option<OwnedAttributeValue> {
+        let mut text = match &self.editing {
+            Some((editing_index, editing_name, text))
+                if *editing_index == index && editing_name == name =>
+            {
+                text.clone()
+            }
+            _ => value.to_string(),
+        };
+
+        let is_valid = value.parse(&text).is_some();
+        let mut text_edit = egui::TextEdit::singleline(&mut text).desired_width(200.0);
+        if !is_valid {
+            text_edit = text_edit.text_color(ui.visuals().error_fg_color);
+        }
+        let response = ui.add(text_edit);
+
+        if response.lost_focus() {
+            self.editing = None;
+            // 解釈できない値は捨てて元の値に戻す
+            return value.parse(&text);
+        }
+        if response.has_focus() {
+            self.editing = Some((index, name.to_string(), text));
+        }
+        None
     }
 }
 
-fn attribute_table_body<'a>(
-    ui: &mut egui::Ui,
-    id: &str,
-    show_all: bool,
-    hide_default: bool,
-    items: impl IntoIterator<Item = (&'a str, (Option<String>, bool))>,
-) {
-    egui::Grid::new(id)
-        .num_columns(2)
-        .spacing([10.0, 4.0])
-        .striped(true)
+fn surface_inspector(ui: &mut egui::Ui, layer: &str, index: usize, surface: &Surface) {
+    let shape = surface.shape.unwrap_or(0);
+    let header = format!(
+        "#{} orientation {} shape {}",
+        index,
+        surface.orientation.unwrap_or(0),
+        shape
+    );
+    egui::CollapsingHeader::new(header)
+        .id_salt((layer, index))
         .show(ui, |ui| {
-            for (label, (value, is_default)) in items {
-                if (show_all || value.is_some()) && !(hide_default && is_default) {
-                    ui.label(label);
-                    if let Some(val) = value {
-                        ui.label(val);
-                    } else {
-                        ui.weak("Not defined");
+            egui::Grid::new(("surface_inspector_table", layer, index))
+                .num_columns(2)
+                .spacing([10.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    let position = surface
+                        .position
+                        .last()
+                        .map(|p| format!("{}, {}, {}", p.x, p.y, p.z))
+                        .unwrap_or_default();
+                    let flags = surface.flags.unwrap_or(0);
+                    let rows = [
+                        ("position", position),
+                        ("orientation", surface.orientation.unwrap_or(0).to_string()),
+                        ("rotation", surface.rotation.unwrap_or(0).to_string()),
+                        (
+                            "shape",
+                            if is_known_surface_shape(shape) {
+                                shape.to_string()
+                            } else {
+                                format!("{} (unknown)", shape)
+                            },
+                        ),
+                        ("trans_type", surface.trans_type.unwrap_or(0).to_string()),
+                        (
+                            "is_reverse_normals",
+                            surface.is_reverse_normals.unwrap_or(false).to_string(),
+                        ),
+                        (
+                            "is_two_sided",
+                            surface.is_two_sided.unwrap_or(false).to_string(),
+                        ),
+                        (
+                            "flags",
+                            format!("{} (0x{:X}) [{}]", flags, flags, flag_labels(flags)),
+                        ),
+                    ];
+                    for (name, value) in rows {
+                        ui.label(name);
+                        ui.label(value);
+                        ui.end_row();
                     }
-                    ui.end_row();
-                }
+                });
+        });
+}
+
+fn element_tree(ui: &mut egui::Ui, element: &XmlElement) {
+    let attributes: Vec<String> = element
+        .attributes
+        .iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, value))
+        .collect();
+    let label = format!("<{}> {}", element.name, attributes.join(" "));
+    if element.children.is_empty() {
+        ui.label(label);
+    } else {
+        ui.collapsing(label, |ui| {
+            for child in &element.children {
+                element_tree(ui, child);
             }
         });
+    }
+}
+
+// 名前の付いた値から選ぶ。ボタンには今の値の名前を表示する
+fn named_value_menu(ui: &mut egui::Ui, name: &str, value: i32) -> Option<OwnedAttributeValue> {
+    let label = attribute_value_label(name, value)?;
+    let names = attribute_value_names(name)?;
+    ui.menu_button(label, |ui| {
+        let mut new_value = None;
+        for (v, value_name) in names {
+            if ui
+                .selectable_label(v == value, format!("{} ({})", v, value_name))
+                .clicked()
+            {
+                new_value = Some(OwnedAttributeValue::I32(Some(v)));
+                ui.close_menu();
+            }
+        }
+        new_value
+    })
+    .inner
+    .flatten()
+}
+
+// ビットごとのチェックボックスで flags を編集する
+// u64 の全ビットを並べるので、長くなる分はスクロールさせる
+fn flags_menu(ui: &mut egui::Ui, flags: u64) -> Option<OwnedAttributeValue> {
+    ui.menu_button("Bits", |ui| {
+        let mut new_flags = None;
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .show(ui, |ui| {
+                for bit in 0..64 {
+                    let mask = 1u64 << bit;
+                    let mut c = flags & mask != 0;
+                    let label = format!("bit {} (0x{:X})", bit, mask);
+                    if ui.checkbox(&mut c, label).changed() {
+                        new_flags = Some(flags ^ mask);
+                    }
+                }
+            });
+        new_flags
+    })
+    .inner
+    .flatten()
+    .map(|flags| OwnedAttributeValue::U64(Some(flags)))
+}
+
+fn default_value(value: AttributeValue<'_>) -> OwnedAttributeValue {
+    match value {
+        AttributeValue::Bool(_) => OwnedAttributeValue::Bool(Some(false)),
+        AttributeValue::I32(_) => OwnedAttributeValue::I32(Some(0)),
+        AttributeValue::U64(_) => OwnedAttributeValue::U64(Some(0)),
+        AttributeValue::F32(_) => OwnedAttributeValue::F32(Some(0.0)),
+        AttributeValue::String(_) => OwnedAttributeValue::String(Some(String::new())),
+    }
+}
+
+fn none_value(value: AttributeValue<'_>) -> OwnedAttributeValue {
+    match value {
+        AttributeValue::Bool(_) => OwnedAttributeValue::Bool(None),
+        AttributeValue::I32(_) => OwnedAttributeValue::I32(None),
+        AttributeValue::U64(_) => OwnedAttributeValue::U64(None),
+        AttributeValue::F32(_) => OwnedAttributeValue::F32(None),
+        AttributeValue::String(_) => OwnedAttributeValue::String(None),
+    }
+}
+
+fn is_default(value: AttributeValue<'_>) -> bool {
+    match value {
+        AttributeValue::Bool(v) => is_default_value(v),
+        AttributeValue::I32(v) => is_default_value(v),
+        AttributeValue::U64(v) => is_default_value(v),
+        AttributeValue::F32(v) => is_default_value(v),
+        AttributeValue::String(v) => is_default_value(v),
+    }
+}
+
+fn is_default_value<T: Default + PartialEq>(value: &Option<T>) -> bool {
+    value.as_ref().is_some_and(|val| *val == T::default())
 }
@@ -1,27 +1,271 @@
-use super::State;
-use egui::Layout;
+use super::{DefinitionSort, State};
+use crate::sw_block_definition::{Category, DefinitionType, SearchQuery};
+use egui::{
+    text::{LayoutJob, TextFormat},
+    Layout,
+};
+use std::{cmp::Ordering, collections::BTreeMap};
+
+const SEARCH_HELP: &str = "\
+wheel\tfilename, name or tags contain the text
+tag:basic\ttags contain the tag
+has:logic_nodes\tthe element or attribute exists
+flag:13\tthe flags bit with this number is set
+category=5 mass>10\tcompare attribute values (=, !=, <, <=, >, >=)
+\"...\"\tquote text containing spaces";
 
 #[derive(Default)]
-pub struct DefinitionSelectPanel {}
+pub struct DefinitionSelectPanel {
+    search_text: String,
+}
 
 impl DefinitionSelectPanel {
     pub fn ui(&mut self, ui: &mut egui::Ui, state: &mut State) {
-        ui.with_layout(Layout::top_down_justified(egui::Align::LEFT), |ui| {
-            let selected_index = *state.selected_definition_index();
-            let mut set_index = None;
-
-            for (i, entry) in state.definitions().iter().enumerate() {
-                if ui
-                    .selectable_label(Some(i) == selected_index, entry.filename())
-                    .clicked()
-                {
-                    set_index = Some(i);
-                }
-            }
+        ui.add(
+            egui::TextEdit::singleline(&mut self.search_text)
+                .hint_text("Search")
+                .desired_width(f32::INFINITY),
+        )
+        .on_hover_text(SEARCH_HELP);
 
-            if let Some(value) = set_index {
-                state.set_selected_definition_index(Some(value));
+        // 解釈できない検索語があれば理由を表示し、絞り込まない
+        let query = match SearchQuery::parse(&self.search_text) {
+            Ok(query) => query,
+            Err(err) => {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+                SearchQuery::default()
+            }
+        };
+        let indices = state.search(&query);
+        if !query.is_empty() {
+            let total = state.definitions().len();
+            let loaded = state.loaded_definition_count();
+            if loaded < total {
+                ui.weak(format!(
+                    "{} definitions (searching {}/{})",
+                    indices.len(),
+                    loaded,
+                    total
+                ));
+            } else {
+                ui.weak(format!("{} / {} definitions", indices.len(), total));
             }
+        }
+        ui.horizontal(|ui| {
+            ui.label("Sort");
+            let mut sort = state.definition_sort();
+            egui::ComboBox::from_id_salt("definition_sort")
+                .selected_text(sort.name())
+                .show_ui(ui, |ui| {
+                    for value in DefinitionSort::ALL {
+                        ui.selectable_value(&mut sort, value, value.name());
+                    }
+                });
+            state.set_definition_sort(sort);
         });
+
+        // 読み込み終わるまでは Loading にまとめる
+        let total = state.definitions().len();
+        let loaded = state.loaded_definition_count();
+        if loaded < total {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak(format!("Loading {} / {}", loaded, total));
+            });
+        }
+        ui.separator();
+
+        let groups = group_definitions(state, &indices);
+        // 検索中は一致したものが見えるように全部開く
+        let open = (!query.is_empty()).then_some(true);
+        let selected_index = *state.selected_definition_index();
+        let mut set_index = None;
+
+        for (category, types) in groups {
+            let count: usize = types.iter().map(|(_, indices)| indices.len()).sum();
+            let label = category.label(|v| Category::from_i32(v).to_string());
+            egui::CollapsingHeader::new(format!("{} ({})", label, count))
+                .id_salt(("definition_category", &category))
+                .open(open)
+                .show(ui, |ui| {
+                    for (definition_type, indices) in types {
+                        let label =
+                            definition_type.label(|v| DefinitionType::from_i32(v).to_string());
+                        egui::CollapsingHeader::new(format!("{} ({})", label, indices.len()))
+                            .id_salt(("definition_type", &category, &definition_type))
+                            .open(open)
+                            .show(ui, |ui| {
+                                ui.with_layout(
+                                    Layout::top_down_justified(egui::Align::LEFT),
+                                    |ui| {
+                                        for i in indices {
+                                            if ui
+                                                .selectable_label(
+                                                    Some(i) == selected_index,
+                                                    definition_label(ui, state, i),
+                                                )
+                                                .clicked()
+                                            {
+                                                set_index = Some(i);
+                                            }
+                                        }
+                                    },
+                                );
+                            });
+                    }
+                });
+        }
+
+        if let Some(value) = set_index {
+            state.set_selected_definition_index(Some(value));
+        }
     }
 }
+
+// ツリーの分類。値の順に並べ、値の無いもの、読み込みに失敗したもの、読み込み前のものは後ろにまとめる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum GroupKey {
+    Value(i32),
+    Undefined,
+    Error,
+    Loading,
+}
+
+impl GroupKey {
+    fn from_attribute(value: Option<i32>) -> Self {
+        value.map_or(Self::Undefined, Self::Value)
+    }
+
+    fn label(self, value_label: impl Fn(i32) -> String) -> String {
+        match self {
+            Self::Value(value) => value_label(value),
+            Self::Undefined => "Undefined".to_string(),
+            Self::Error => "Error".to_string(),
+            Self::Loading => "Loading".to_string(),
+        }
+    }
+}
+
+type DefinitionGroups = Vec<(GroupKey, Vec<(GroupKey, Vec<usize>)>)>;
+
+// category ごと、その中で type ごとにまとめ、それぞれを並び順の設定で並べる
+fn group_definitions(state: &mut State, indices: &[usize]) -> DefinitionGroups {
+    let sort = state.definition_sort();
+    let mut groups: BTreeMap<GroupKey, BTreeMap<GroupKey, Vec<(SortKey, usize)>>> = BTreeMap::new();
+
+    for &i in indices {
+        let definition = &mut state.definitions_mut()[i];
+        let filename = definition.filename().to_lowercase();
+        let (category, definition_type, key) = if !definition.is_loaded() {
+            (
+                GroupKey::Loading,
+                GroupKey::Loading,
+                SortKey::text(filename),
+            )
+        } else {
+            match definition.data() {
+                Ok(data) => {
+                    let key = match sort {
+                        DefinitionSort::Filename => SortKey::text(filename),
+                        DefinitionSort::Name => SortKey {
+                            number: None,
+                            text: data.name.clone().unwrap_or_default().to_lowercase(),
+                        },
+                        DefinitionSort::Mass => SortKey::number(data.mass, filename),
+                        DefinitionSort::Value => SortKey::number(data.value, filename),
+                        DefinitionSort::Revision => {
+                            SortKey::number(data.revision.map(|v| v as f32), filename)
+                        }
+                    };
+                    (
+                        GroupKey::from_attribute(data.category),
+                        GroupKey::from_attribute(data.definition_type),
+                        key,
+                    )
+                }
+                Err(_) => (GroupKey::Error, GroupKey::Error, SortKey::text(filename)),
+            }
+        };
+        groups
+            .entry(category)
+            .or_default()
+            .entry(definition_type)
+            .or_default()
+            .push((key, i));
+    }
+
+    groups
+        .into_iter()
+        .map(|(category, types)| {
+            let types = types
+                .into_iter()
+                .map(|(definition_type, mut entries)| {
+                    entries.sort_by(|a, b| a.0.compare(&b.0));
+                    (
+                        definition_type,
+                        entries.into_iter().map(|(_, i)| i).collect(),
+                    )
+                })
+                .collect();
+            (category, types)
+        })
+        .collect()
+}
+
+// 数値で並べるときは値の無いものを後ろにし、同じ値はファイル名で並べる
+struct SortKey {
+    number: Option<f32>,
+    text: String,
+}
+
+impl SortKey {
+    fn text(text: String) -> Self {
+        Self { number: None, text }
+    }
+
+    fn number(number: Option<f32>, text: String) -> Self {
+        Self { number, text }
+    }
+
+    fn compare(&self, other: &Self) -> Ordering {
+        match (self.number, other.number) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+        .then_with(|| self.text.cmp(&other.text))
+    }
+}
+
+// ファイル名の横に name 属性を薄く表示する
+fn definition_label(ui: &egui::Ui, state: &mut State, index: usize) -> LayoutJob {
+    let definition = &mut state.definitions_mut()[index];
+    let mut filename = definition.filename();
+    if definition.is_dirty() {
+        filename += " *";
+    }
+    let name = if definition.is_loaded() {
+        definition
+            .data()
+            .ok()
+            .and_then(|data| data.name.clone())
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+
+    let font_id = egui::TextStyle::Button.resolve(ui.style());
+    let mut job = LayoutJob::default();
+    job.append(
+        &filename,
+        0.0,
+        TextFormat::simple(font_id.clone(), ui.visuals().text_color()),
+    );
+    job.append(
+        &name,
+        8.0,
+        TextFormat::simple(font_id, ui.visuals().weak_text_color()),
+    );
+    job
+}
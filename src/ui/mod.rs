@@ -1,7 +1,7 @@
 mod app;
 pub use app::MainApp;
 mod state;
-pub use state::State;
+pub use state::{DefinitionSort, State};
 mod definition_selelct_panel;
 pub use definition_selelct_panel::DefinitionSelectPanel;
 mod definition_detail_panel;
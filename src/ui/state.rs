@@ -1,6 +1,14 @@
-use crate::sw_block_definition::{SwBlockDefinition, SwBlockDefinitionMeshKey};
+use crate::sw_block_definition::{
+    definition_schema::OwnedAttributeValue, DefinitionLoader, Loaded, SearchIndex, SearchQuery,
+    SwBlockDefinition, SwBlockDefinitionDataError, SwBlockDefinitionMeshKey,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::sw_block_definition::{DefinitionWatcher, DiskChange};
 use enum_map::{self, EnumMap};
-use std::{fs, io, path::Path};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
 
 macro_rules! getter_setter {
     ($target:ident, $name:ident, $setter_name:ident, $type:ty) => {
@@ -19,16 +27,69 @@ macro_rules! getter_setter {
     };
 }
 
+// 定義一覧の並び順
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionSort {
+    Filename,
+    Name,
+    Mass,
+    Value,
+    Revision,
+}
+
+impl DefinitionSort {
+    pub const ALL: [Self; 5] = [
+        Self::Filename,
+        Self::Name,
+        Self::Mass,
+        Self::Value,
+        Self::Revision,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Filename => "Filename",
+            Self::Name => "Name",
+            Self::Mass => "Mass",
+            Self::Value => "Value",
+            Self::Revision => "Revision",
+        }
+    }
+}
+
+// 古いバージョンで保存した状態も読めるように、無いフィールドは既定値にする
 #[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct State {
+    rom_path: Option<PathBuf>,
     definitions: Vec<SwBlockDefinition>,
     selected_definition_index: Option<usize>,
+    definition_sort: DefinitionSort,
     show_all_attributes: bool,
     hide_default_attributes: bool,
     show_xyz_axis: bool,
     show_surfaces: bool,
     show_surface_edge: bool,
+    show_buoyancy_surfaces: bool,
+    show_physics_only: bool,
+    show_logic_nodes: bool,
+    show_couplings: bool,
+    show_voxels: bool,
+    selected_coupling_index: Option<usize>,
     show_mesh: EnumMap<SwBlockDefinitionMeshKey, bool>,
+    // ペイントスロット 1～3 の色 (sRGB)
+    paint_colors: [[u8; 3]; 3],
+    thumbnail_size: u32,
+    screenshot_width: u32,
+    screenshot_height: u32,
+    screenshot_transparent: bool,
+    #[serde(skip)]
+    search_index: Option<SearchIndex>,
+    #[serde(skip)]
+    loader: Option<DefinitionLoader>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    watcher: Option<DefinitionWatcher>,
     #[serde(skip)]
     changed: Option<bool>,
 }
@@ -40,14 +101,31 @@ impl Default for State {
             show_mesh[key] = true;
         }
         Self {
+            rom_path: None,
             definitions: Vec::new(),
             selected_definition_index: None,
+            definition_sort: DefinitionSort::Filename,
             show_all_attributes: false,
             hide_default_attributes: false,
             show_xyz_axis: true,
             show_surfaces: true,
             show_surface_edge: true,
+            show_buoyancy_surfaces: false,
+            show_physics_only: false,
+            show_logic_nodes: true,
+            show_couplings: true,
+            show_voxels: false,
+            selected_coupling_index: None,
             show_mesh,
+            paint_colors: [[255; 3]; 3],
+            thumbnail_size: 256,
+            screenshot_width: 1920,
+            screenshot_height: 1080,
+            screenshot_transparent: false,
+            search_index: None,
+            loader: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            watcher: None,
             changed: None,
         }
     }
@@ -71,13 +149,25 @@ impl State {
         &self.definitions
     }
 
+    pub fn definitions_mut(&mut self) -> &mut Vec<SwBlockDefinition> {
+        &mut self.definitions
+    }
+
     pub fn selected_definition_index(&self) -> &Option<usize> {
         &self.selected_definition_index
     }
 
+    // 選択を外した定義のメッシュは捨て、選択中の定義のメッシュだけをメモリに置く
     pub fn set_selected_definition_index(&mut self, value: Option<usize>) {
         if self.selected_definition_index != value {
+            if let Some(definition) = self
+                .selected_definition_index
+                .and_then(|i| self.definitions.get_mut(i))
+            {
+                definition.unload_meshes();
+            }
             self.selected_definition_index = value;
+            self.selected_coupling_index = None;
             self.changed();
         }
     }
@@ -97,9 +187,275 @@ impl State {
         self.definitions.get_mut(self.selected_definition_index?)
     }
 
+    pub fn set_selected_attribute(&mut self, name: &str, value: OwnedAttributeValue) {
+        if let Some(definition) = self.selected_definition() {
+            if let Ok(true) = definition.set_attribute(name, value) {
+                self.update_selected_search_entry();
+                self.changed();
+            }
+        }
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(definition) = self.selected_definition() {
+            if definition.undo() {
+                self.update_selected_search_entry();
+                self.changed();
+            }
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(definition) = self.selected_definition() {
+            if definition.redo() {
+                self.update_selected_search_entry();
+                self.changed();
+            }
+        }
+    }
+
+    // 編集した定義の分だけ索引を作り直す
+    fn update_selected_search_entry(&mut self) {
+        if let (Some(index), Some(i)) = (&mut self.search_index, self.selected_definition_index) {
+            if let Some(definition) = self.definitions.get(i) {
+                index.update(i, definition);
+            }
+        }
+    }
+
+    // 読み込み済みのときだけ選択中の定義を返す
+    // 読み込み中に data() を呼ぶと UI スレッドで読み込んでしまうので、パネルからはこちらを使う
+    pub fn loaded_selected_definition(&mut self) -> Option<&mut SwBlockDefinition> {
+        self.selected_definition().filter(|d| d.is_loaded())
+    }
+
+    // 選択中の定義かそのメッシュを読み込み中か
+    pub fn is_selected_definition_loading(&self) -> bool {
+        self.selected_definition_index
+            .and_then(|i| self.definitions.get(i))
+            .is_some_and(|d| !d.is_loaded() || d.needs_meshes())
+    }
+
+    // 描画フレームごとに呼び、ワーカースレッドで読み込み終わった定義とメッシュを反映する
+    // 未読み込みの定義は全て予約し、選択中の定義は先に読み込む
+    // メッシュは選択中の定義の分だけ読み込む
+    // まだ読み込み中のものがあれば true を返す
+    pub fn update_loading(&mut self) -> bool {
+        let results = self.loader.get_or_insert_with(DefinitionLoader::new).poll();
+        for result in results {
+            let Some(definition) = self.definitions.get_mut(result.index) else {
+                continue;
+            };
+            if definition.path() != result.path {
+                continue;
+            }
+            match result.loaded {
+                Loaded::Definition(loaded, modified) => {
+                    // 読み込み中に書き換えられていたら捨てて、下で読み込み直す
+                    if !definition.set_loaded(loaded, modified) {
+                        continue;
+                    }
+                    // 読み込み終わった定義から検索できるようにする
+                    if let Some(index) = &mut self.search_index {
+                        index.update(result.index, definition);
+                    }
+                }
+                Loaded::Meshes(data, meshes) => {
+                    // 選択を外した後に届いたメッシュは持たない
+                    if Some(result.index) != self.selected_definition_index {
+                        continue;
+                    }
+                    definition.set_loaded_meshes(&data, meshes);
+                }
+            }
+            if Some(result.index) == self.selected_definition_index {
+                self.changed();
+            }
+        }
+
+        let loader = self.loader.as_mut().unwrap();
+        if let Some(i) = self.selected_definition_index {
+            if let Some(definition) = self.definitions.get(i) {
+                if !definition.is_loaded() {
+                    loader.request(i, definition, true);
+                } else if let Some(data) = definition
+                    .loaded_data()
+                    .filter(|_| definition.needs_meshes() && !loader.is_meshes_pending(i))
+                {
+                    loader.request_meshes(i, definition, data);
+                }
+            }
+        }
+        for (i, definition) in self.definitions.iter().enumerate() {
+            if !definition.is_loaded() && !loader.is_pending(i) {
+                loader.request(i, definition, false);
+            }
+        }
+        loader.pending_count() > 0
+    }
+
+    // 描画フレームごとに呼び、ROM フォルダ内で変更されたファイルを反映する
+    // 書き換えられた定義は読み込み直し、追加、削除された定義は一覧に加える、取り除く
+    // 編集中の定義は編集内容を残し、変更または削除されたことだけを記録する
+    // (読み込み直すか上書き保存するかは利用者に選ばせる)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn update_watching(&mut self) {
+        // 古いバージョンで保存した状態には rom_path が無いので、読み込んである定義の場所から決める
+        if self.rom_path.is_none() {
+            self.rom_path = self
+                .definitions
+                .first()
+                .map(|definition| definition.rom_path().to_path_buf());
+        }
+        let Some(rom_path) = self.rom_path.clone() else {
+            self.watcher = None;
+            return;
+        };
+        let watcher = match &mut self.watcher {
+            Some(watcher) if watcher.rom_path() == rom_path => watcher,
+            watcher => watcher.insert(DefinitionWatcher::new(&rom_path)),
+        };
+        let paths = watcher.poll();
+        if paths.is_empty() {
+            return;
+        }
+
+        for path in paths {
+            self.apply_file_change(&rom_path, &path);
+        }
+    }
+
+    // 変更のあった 1 つのファイルを反映する
+    #[cfg(not(target_arch = "wasm32"))]
+    fn apply_file_change(&mut self, rom_path: &Path, path: &Path) {
+        let definitions_path = rom_path.join("data").join("definitions");
+        let is_definition = path.parent() == Some(definitions_path.as_path())
+            && path.extension().is_some_and(|ext| ext == "xml");
+        if !is_definition {
+            self.reload_meshes(path);
+            return;
+        }
+
+        match self.definitions.iter().position(|d| d.path() == path) {
+            Some(i) if self.definitions[i].is_dirty() => {
+                let definition = &mut self.definitions[i];
+                let change = if !path.is_file() {
+                    Some(DiskChange::Deleted)
+                } else if definition.is_modified_on_disk() {
+                    Some(DiskChange::Modified)
+                } else {
+                    None
+                };
+                if let Some(change) = change {
+                    definition.set_disk_change(change);
+                    if Some(i) == self.selected_definition_index {
+                        self.changed();
+                    }
+                }
+            }
+            Some(i) if !path.is_file() => self.remove_definition(i),
+            Some(i) => {
+                if self.definitions[i].is_modified_on_disk() {
+                    self.unload_definition(i);
+                }
+            }
+            None if path.is_file() => {
+                if let Some(definition) = SwBlockDefinition::new(rom_path, path) {
+                    self.definitions.push(definition);
+                    self.search_index = None;
+                }
+            }
+            None => {}
+        }
+    }
+
+    // 読み込んだ内容を捨てて、ワーカースレッドで読み込み直させる
+    #[cfg(not(target_arch = "wasm32"))]
+    fn unload_definition(&mut self, index: usize) {
+        let definition = &mut self.definitions[index];
+        definition.unload();
+        if let Some(search_index) = &mut self.search_index {
+            search_index.update(index, definition);
+        }
+        if Some(index) == self.selected_definition_index {
+            self.changed();
+        }
+    }
+
+    // 選択中の定義の編集を捨ててファイルの内容に戻す
+    // ファイルが削除されていたら一覧から取り除く
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload_selected_definition(&mut self) {
+        let Some(i) = self.selected_definition_index else {
+            return;
+        };
+        match self.definitions.get(i).map(|d| d.path().is_file()) {
+            Some(true) => self.unload_definition(i),
+            Some(false) => self.remove_definition(i),
+            None => {}
+        }
+    }
+
+    // メッシュを捨てるだけにして、選択中の定義の分は update_loading で読み込み直させる
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_meshes(&mut self, path: &Path) {
+        let mut selected_changed = false;
+        for (i, definition) in self.definitions.iter_mut().enumerate() {
+            if definition.uses_mesh(path) {
+                definition.unload_meshes();
+                selected_changed |= Some(i) == self.selected_definition_index;
+            }
+        }
+        if selected_changed {
+            self.changed();
+        }
+    }
+
+    // 添字がずれるので選択を詰め、読み込みの予約をやり直す
+    #[cfg(not(target_arch = "wasm32"))]
+    fn remove_definition(&mut self, index: usize) {
+        self.definitions.remove(index);
+        match self.selected_definition_index {
+            Some(i) if i == index => {
+                self.selected_definition_index = None;
+                self.selected_coupling_index = None;
+                self.changed();
+            }
+            Some(i) if i > index => self.selected_definition_index = Some(i - 1),
+            _ => {}
+        }
+        self.search_index = None;
+        if let Some(loader) = &mut self.loader {
+            loader.reset();
+        }
+    }
+
+    pub fn loaded_definition_count(&self) -> usize {
+        self.definitions.iter().filter(|d| d.is_loaded()).count()
+    }
+
+    // 一致した定義の添字を返す
+    // 索引には読み込み済みの定義だけが載るので、読み込み中は一部の定義だけから探す
+    pub fn search(&mut self, query: &SearchQuery) -> Vec<usize> {
+        if query.is_empty() {
+            return (0..self.definitions.len()).collect();
+        }
+        self.search_index
+            .get_or_insert_with(|| SearchIndex::build(&self.definitions))
+            .search(query)
+    }
+
+    pub fn save_selected_definition(&mut self) -> Result<(), SwBlockDefinitionDataError> {
+        if let Some(definition) = self.selected_definition() {
+            definition.save()?;
+            self.changed();
+        }
+        Ok(())
+    }
+
     pub fn open_rom_directory<P: AsRef<Path>>(&mut self, rom_path: P) -> io::Result<()> {
         // ディレクトリ内の .xml ファイルを列挙
-        match fs::read_dir(rom_path.as_ref().join("data\\definitions")) {
+        match fs::read_dir(rom_path.as_ref().join("data").join("definitions")) {
             Ok(dir) => {
                 self.definitions = dir
                     .filter_map(|entry| {
@@ -113,19 +469,31 @@ impl State {
                         None
                     })
                     .collect();
+                self.rom_path = Some(rom_path.as_ref().to_path_buf());
                 self.selected_definition_index = None;
+                self.selected_coupling_index = None;
+                self.search_index = None;
+                if let Some(loader) = &mut self.loader {
+                    loader.reset();
+                }
                 self.changed();
                 Ok(())
             }
             Err(err) => {
+                self.rom_path = None;
                 self.definitions = Vec::new();
                 self.selected_definition_index = None;
+                self.search_index = None;
+                if let Some(loader) = &mut self.loader {
+                    loader.reset();
+                }
                 Err(err)
             }
         }
     }
 }
 
+getter_setter!(State, definition_sort, set_definition_sort, DefinitionSort);
 getter_setter!(State, show_all_attributes, set_show_all_sttributes, bool);
 getter_setter!(
     State,
@@ -136,3 +504,115 @@ getter_setter!(
 getter_setter!(State, show_xyz_axis, set_show_xyz_axis, bool);
 getter_setter!(State, show_surfaces, set_show_surfaces, bool);
 getter_setter!(State, show_surface_edge, set_show_surface_edge, bool);
+getter_setter!(
+    State,
+    show_buoyancy_surfaces,
+    set_show_buoyancy_surfaces,
+    bool
+);
+getter_setter!(State, show_physics_only, set_show_physics_only, bool);
+getter_setter!(State, show_logic_nodes, set_show_logic_nodes, bool);
+getter_setter!(State, show_couplings, set_show_couplings, bool);
+getter_setter!(State, show_voxels, set_show_voxels, bool);
+getter_setter!(State, paint_colors, set_paint_colors, [[u8; 3]; 3]);
+getter_setter!(State, thumbnail_size, set_thumbnail_size, u32);
+getter_setter!(State, screenshot_width, set_screenshot_width, u32);
+getter_setter!(State, screenshot_height, set_screenshot_height, u32);
+getter_setter!(
+    State,
+    screenshot_transparent,
+    set_screenshot_transparent,
+    bool
+);
+getter_setter!(
+    State,
+    selected_coupling_index,
+    set_selected_coupling_index,
+    Option<usize>
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_state_saved_by_older_version() {
+        let state: State = serde_json::from_str(
+            r#"{"definitions":[],"selected_definition_index":null,"show_all_attributes":true}"#,
+        )
+        .unwrap();
+        assert!(state.show_all_attributes());
+        assert_eq!(state.thumbnail_size(), 256);
+        assert_eq!(state.definition_sort(), DefinitionSort::Filename);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn keeps_edited_definitions_changed_on_disk() {
+        let rom_path = std::env::temp_dir().join(format!("swbd-state-{}", std::process::id()));
+        let definitions_path = rom_path.join("data").join("definitions");
+        fs::create_dir_all(&definitions_path).unwrap();
+        let edited_path = definitions_path.join("a.xml");
+        let other_path = definitions_path.join("b.xml");
+        let mesh_path = rom_path.join("meshes").join("a.mesh");
+        fs::write(
+            &edited_path,
+            r#"<definition name="A" mass="1" mesh_data_name="meshes/a.mesh"/>"#,
+        )
+        .unwrap();
+        fs::write(&other_path, r#"<definition name="B"/>"#).unwrap();
+
+        let mut state = State::default();
+        state.open_rom_directory(&rom_path).unwrap();
+        let index = state
+            .definitions()
+            .iter()
+            .position(|d| d.path() == edited_path)
+            .unwrap();
+        state.set_selected_definition_index(Some(index));
+        state.selected_definition().unwrap().meshes();
+
+        // 参照しているメッシュが変わったらメッシュだけ捨てる
+        state.apply_file_change(&rom_path, &mesh_path);
+        assert!(state.selected_definition().unwrap().needs_meshes());
+        // 書き方の違うパスでも同じファイルなら同じメッシュとみなす
+        fs::create_dir_all(mesh_path.parent().unwrap()).unwrap();
+        fs::write(&mesh_path, b"").unwrap();
+        state.selected_definition().unwrap().meshes();
+        let other_mesh_path = definitions_path.join("..").join("..").join("meshes/a.mesh");
+        state.apply_file_change(&rom_path, &other_mesh_path);
+        assert!(state.selected_definition().unwrap().needs_meshes());
+
+        state.set_selected_attribute("mass", OwnedAttributeValue::F32(Some(2.0)));
+        fs::remove_file(&edited_path).unwrap();
+        state.apply_file_change(&rom_path, &edited_path);
+        let definition = state.selected_definition().unwrap();
+        assert_eq!(definition.disk_change(), Some(DiskChange::Deleted));
+        assert!(definition.is_dirty());
+
+        fs::write(&edited_path, r#"<definition name="A2"/>"#).unwrap();
+        state.apply_file_change(&rom_path, &edited_path);
+        let definition = state.selected_definition().unwrap();
+        assert_eq!(definition.disk_change(), Some(DiskChange::Modified));
+        assert_eq!(definition.data().unwrap().mass, Some(2.0));
+
+        // 保存すると変更の記録は消える
+        state.save_selected_definition().unwrap();
+        assert_eq!(state.selected_definition().unwrap().disk_change(), None);
+
+        // 編集していない定義は削除されたら一覧から取り除く
+        fs::remove_file(&other_path).unwrap();
+        state.apply_file_change(&rom_path, &other_path);
+        assert_eq!(state.definitions().len(), 1);
+        assert_eq!(state.selected_definition().unwrap().path(), edited_path);
+
+        // 古いバージョンで保存した状態は rom_path が無いので定義の場所から決める
+        let mut saved = serde_json::to_value(&state).unwrap();
+        saved.as_object_mut().unwrap().remove("rom_path");
+        let mut restored: State = serde_json::from_value(saved).unwrap();
+        restored.update_watching();
+        assert_eq!(restored.rom_path.as_deref(), Some(rom_path.as_path()));
+
+        fs::remove_dir_all(&rom_path).unwrap();
+    }
+}